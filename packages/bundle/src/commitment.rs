@@ -0,0 +1,97 @@
+use smart_account_auth::{CredentialId, CredentialRecord};
+use types::{
+    errors::{AuthError, StorageError},
+    merkle::{credential_leaf, merkle_proof, merkle_root, CredentialProof, CredentialsQueryMsg},
+    stores::CREDENTIALS_ROOT,
+    wasm::{to_json_binary, Binary, StdError, StdResult, Storage},
+};
+use crate::utils::get_credentials;
+
+
+
+/// Merkle root over the stored credentials if there are any. Accounts that saved their credentials
+/// before the root was stored get it computed on the fly until `update_credentials_root` saves it
+pub fn credentials_root(
+    storage: &dyn Storage
+) -> Option<Binary> {
+    match CREDENTIALS_ROOT.may_load(storage).ok().flatten() {
+        Some(root) => Some(root),
+        None => {
+            let leaves = credential_leaves(&get_credentials(storage).ok()?).ok()?;
+            merkle_root(&leaves).map(|root| Binary::from(root.to_vec()))
+        }
+    }
+}
+
+
+
+/// Inclusion proof of the stored credential that can be checked with `verify_credential_proof`
+pub fn credential_proof(
+    storage: &dyn Storage,
+    id: &CredentialId,
+) -> Result<CredentialProof, AuthError> {
+    let records = get_credentials(storage)?;
+    let index = records
+        .iter()
+        .position(|(k, _)| k == id)
+        .ok_or(StorageError::NotFound)?;
+    let leaves = credential_leaves(&records)?;
+    let root = merkle_root(&leaves).ok_or(StorageError::NotFound)?;
+    Ok(CredentialProof {
+        id: id.clone(),
+        info: records[index].1.clone(),
+        proof: merkle_proof(&leaves, index),
+        root: Binary::from(root.to_vec()),
+    })
+}
+
+
+
+/// Recompute the root after the stored credentials have changed. The whole tree is rebuilt 
+/// from every record so the gas of each save, add or remove grows linearly with their number.
+/// Meant to be called on migration by the accounts that have credentials without a stored root
+pub fn update_credentials_root(
+    storage: &mut dyn Storage
+) -> Result<(), StorageError> {
+    let leaves = credential_leaves(&get_credentials(storage)?)?;
+    match merkle_root(&leaves) {
+        Some(root) => CREDENTIALS_ROOT.save(storage, &Binary::from(root.to_vec()))
+            .map_err(|e| StorageError::Write("credentials root".to_string(), e.to_string())),
+        None => {
+            CREDENTIALS_ROOT.remove(storage);
+            Ok(())
+        }
+    }
+}
+
+
+
+pub fn handle_credentials_query(
+    storage: &dyn Storage,
+    query: CredentialsQueryMsg,
+) -> StdResult<Binary> {
+    match query {
+        CredentialsQueryMsg::CredentialsRoot {} => {
+            to_json_binary(&credentials_root(storage))
+        },
+        CredentialsQueryMsg::CredentialProof { 
+            id 
+        } => {
+            let proof = credential_proof(storage, &id)
+                .map_err(|e| StdError::generic_err(e.to_string()))?;
+            to_json_binary(&proof)
+        }
+    }
+}
+
+
+
+fn credential_leaves(
+    records: &[CredentialRecord]
+) -> Result<Vec<[u8; 32]>, StorageError> {
+    records
+        .iter()
+        .map(|(id, info)| credential_leaf(id, info))
+        .collect::<Result<Vec<[u8; 32]>, _>>()
+        .map_err(|e| StorageError::Read("credentials".to_string(), e.to_string()))
+}
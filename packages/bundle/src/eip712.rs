@@ -0,0 +1,305 @@
+use saa_schema::saa_type;
+use smart_account_auth::Credential;
+#[cfg(any(feature = "session", feature = "signed", feature = "ibc"))]
+use smart_account_auth::{msgs::SignedDataMsg, CredentialName};
+use tiny_keccak::{Hasher, Keccak};
+use types::{
+    envelope::{BlockBound, ValidityBounds},
+    errors::{EnvelopeError, ReplayError, WasmAuthError},
+    wasm::{ensure, from_json, Api, Binary, Env, Timestamp, Uint64}
+};
+
+
+/// Name of the EIP-712 domain of the typed data signed by Ethereum wallets
+pub const EIP712_DOMAIN_NAME : &str = "saa-wasm";
+/// Version of the EIP-712 domain of the typed data signed by Ethereum wallets
+pub const EIP712_DOMAIN_VERSION : &str = "1";
+
+const DOMAIN_TYPE : &str = "EIP712Domain(string name,string version,string chainId,string verifyingContract)";
+const MESSAGE_TYPE : &str = "MsgDataToSign(uint64 nonce,string[] messages)";
+const BOUNDED_MESSAGE_TYPE : &str = "MsgDataToSign(uint64 nonce,string[] messages,ValidityBounds bounds)\
+    ValidityBounds(uint64 valid_after_height,uint64 valid_after_time,uint64 valid_until_height,uint64 valid_until_time)";
+const BOUNDS_TYPE : &str = "ValidityBounds(uint64 valid_after_height,uint64 valid_after_time,uint64 valid_until_height,uint64 valid_until_time)";
+
+
+
+/// Domain of the typed data. The Cosmos chain id and the bech32 address of the contract
+/// aren't an `uint256` and an `address` so both of them are encoded as `string`
+#[saa_type]
+pub struct Eip712Domain {
+    pub name                :   String,
+    pub version             :   String,
+    #[serde(rename = "chainId")]
+    pub chain_id            :   String,
+    #[serde(rename = "verifyingContract")]
+    pub verifying_contract  :   String,
+}
+
+
+/// Nonce and messages of `MsgDataToSign`. The chain and the contract are in the domain
+#[saa_type]
+pub struct TypedMsgData {
+    pub nonce               :   Uint64,
+    pub messages            :   Vec<String>,
+    /// Optional validity bounds. The message is signed as the bounded type if they are given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounds              :   Option<TypedBounds>,
+}
+
+
+/// Validity bounds of the typed data with the times in seconds. Zeros stand for the missing bounds
+#[saa_type]
+#[derive(Default)]
+pub struct TypedBounds {
+    pub valid_after_height  :   Uint64,
+    pub valid_after_time    :   Uint64,
+    pub valid_until_height  :   Uint64,
+    pub valid_until_time    :   Uint64,
+}
+
+
+/// Alternative to the JSON envelope that Ethereum wallets sign with `eth_signTypedData_v4`.
+/// Wallets get it together with the types of the `EIP712Domain` and of the primary `MsgDataToSign`
+#[saa_type]
+pub struct TypedDataToSign {
+    pub domain              :   Eip712Domain,
+    pub message             :   TypedMsgData,
+}
+
+
+
+impl Eip712Domain {
+    /// Domain of the envelopes signed for the contract on the current chain
+    pub fn new(env: &Env) -> Self {
+        Self {
+            name: EIP712_DOMAIN_NAME.to_string(),
+            version: EIP712_DOMAIN_VERSION.to_string(),
+            chain_id: env.block.chain_id.clone(),
+            verifying_contract: env.contract.address.to_string(),
+        }
+    }
+}
+
+
+
+impl TypedDataToSign {
+
+    pub fn new(env: &Env, nonce: u64, messages: Vec<String>) -> Self {
+        Self {
+            domain: Eip712Domain::new(env),
+            message: TypedMsgData {
+                nonce: Uint64::new(nonce),
+                messages,
+                bounds: None,
+            },
+        }
+    }
+
+    /// Envelope that can only be used within the given block bounds
+    pub fn with_bounds(env: &Env, nonce: u64, messages: Vec<String>, bounds: &ValidityBounds) -> Self {
+        let mut typed = Self::new(env, nonce, messages);
+        typed.message.bounds = Some(TypedBounds::from(bounds));
+        typed
+    }
+
+    /// Envelope in the signed data if it has been encoded as typed data
+    pub fn from_data(data: &Binary) -> Option<Self> {
+        from_json(data).ok()
+    }
+
+    /// The digest that has been signed: `keccak256("\x19\x01" ‖ domainSeparator ‖ hashStruct(message))`
+    pub fn digest(&self) -> [u8; 32] {
+        let domain = keccak(&[
+            keccak(DOMAIN_TYPE.as_bytes()),
+            keccak(self.domain.name.as_bytes()),
+            keccak(self.domain.version.as_bytes()),
+            keccak(self.domain.chain_id.as_bytes()),
+            keccak(self.domain.verifying_contract.as_bytes()),
+        ].concat());
+
+        let messages = self.message.messages
+            .iter()
+            .map(|m| keccak(m.as_bytes()))
+            .collect::<Vec<[u8; 32]>>()
+            .concat();
+
+        let mut fields = vec![
+            uint64(self.message.nonce),
+            keccak(&messages),
+        ];
+        let message_type = match &self.message.bounds {
+            Some(bounds) => {
+                fields.push(keccak(&[
+                    keccak(BOUNDS_TYPE.as_bytes()),
+                    uint64(bounds.valid_after_height),
+                    uint64(bounds.valid_after_time),
+                    uint64(bounds.valid_until_height),
+                    uint64(bounds.valid_until_time),
+                ].concat()));
+                BOUNDED_MESSAGE_TYPE
+            },
+            None => MESSAGE_TYPE,
+        };
+        let message = keccak(&[&keccak(message_type.as_bytes())[..], &fields.concat()[..]].concat());
+
+        keccak(&[&[0x19u8, 0x01][..], &domain[..], &message[..]].concat())
+    }
+
+    /// Check that the envelope is meant for this contract, chain and nonce.
+    /// The messages are only compared if given
+    pub fn check(
+        &self,
+        env: &Env,
+        nonce: u64,
+        messages: Option<&[String]>
+    ) -> Result<(), WasmAuthError> {
+        let (domain, msg) = (&self.domain, &self.message);
+        ensure!(
+            domain.name == EIP712_DOMAIN_NAME && domain.version == EIP712_DOMAIN_VERSION, 
+            EnvelopeError::Invalid("unknown EIP-712 domain".to_string())
+        );
+        ensure!(domain.chain_id == env.block.chain_id, EnvelopeError::ChainId);
+        ensure!(domain.verifying_contract == env.contract.address.as_str(), EnvelopeError::Contract);
+        ensure!(msg.nonce.u64() == nonce, ReplayError::InvalidNonce(nonce));
+        if let Some(bounds) = &msg.bounds {
+            bounds.validity()?.check(&env.block)?;
+        }
+        if let Some(messages) = messages {
+            ensure!(
+                msg.messages.len() == messages.len(), 
+                EnvelopeError::MessageCount(msg.messages.len(), messages.len())
+            );
+            if let Some(index) = msg.messages.iter().zip(messages).position(|(a, b)| a != b) {
+                return Err(EnvelopeError::Message(index).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Recover the Ethereum address from the signature and compare it with the one of the signer
+    pub fn verify(
+        &self,
+        api: &dyn Api,
+        signature: &[u8],
+        signer: &str,
+    ) -> Result<(), WasmAuthError> {
+        ensure!(signature.len() == 65, WasmAuthError::generic("Invalid length of EIP-712 signature"));
+        let recovery = match signature[64] {
+            27 | 28 => signature[64] - 27,
+            v => v,
+        };
+        let pubkey = api
+            .secp256k1_recover_pubkey(&self.digest(), &signature[..64], recovery)
+            .map_err(|e| WasmAuthError::generic(e.to_string()))?;
+        ensure!(pubkey.len() == 65, WasmAuthError::generic("Invalid recovered public key"));
+
+        let address = keccak(&pubkey[1..])[12..]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+
+        let signer = signer.trim_start_matches("0x").to_lowercase();
+        ensure!(address == signer, WasmAuthError::generic("Signer mismatch in signed typed data"));
+        Ok(())
+    }
+}
+
+
+
+impl TypedBounds {
+    /// Bounds to check against the current block. Each of them can be either a height or a time
+    pub fn validity(&self) -> Result<ValidityBounds, EnvelopeError> {
+        let bound = |height: Uint64, time: Uint64, name: &str| match (height.u64(), time.u64()) {
+            (0, 0) => Ok(None),
+            (height, 0) => Ok(Some(BlockBound::Height(height))),
+            (0, time) => Ok(Some(BlockBound::Time(Timestamp::from_seconds(time)))),
+            _ => Err(EnvelopeError::Invalid(format!("{} has both a height and a time", name))),
+        };
+        Ok(ValidityBounds {
+            valid_after: bound(self.valid_after_height, self.valid_after_time, "valid_after")?,
+            valid_until: bound(self.valid_until_height, self.valid_until_time, "valid_until")?,
+        })
+    }
+}
+
+
+impl From<&ValidityBounds> for TypedBounds {
+    fn from(bounds: &ValidityBounds) -> Self {
+        let split = |bound: &Option<BlockBound>| match bound {
+            Some(BlockBound::Height(height)) => (Uint64::new(*height), Uint64::zero()),
+            Some(BlockBound::Time(time)) => (Uint64::zero(), Uint64::new(time.seconds())),
+            None => (Uint64::zero(), Uint64::zero()),
+        };
+        let (valid_after_height, valid_after_time) = split(&bounds.valid_after);
+        let (valid_until_height, valid_until_time) = split(&bounds.valid_until);
+        Self { valid_after_height, valid_after_time, valid_until_height, valid_until_time }
+    }
+}
+
+
+
+/// Envelope of an Ethereum credential if its message has been encoded as typed data
+pub fn typed_credential(
+    cred: &Credential
+) -> Option<TypedDataToSign> {
+    match cred {
+        Credential::EthPersonalSign(eth) => TypedDataToSign::from_data(&eth.message),
+        _ => None,
+    }
+}
+
+
+
+/// Use instead of `Credential::verify` for the Ethereum credentials that have signed typed data
+pub fn verify_typed_credential(
+    api: &dyn Api,
+    cred: &Credential,
+) -> Result<(), WasmAuthError> {
+    match cred {
+        Credential::EthPersonalSign(eth) => {
+            let typed = TypedDataToSign::from_data(&eth.message)
+                .ok_or_else(|| WasmAuthError::generic("Message is not an EIP-712 envelope"))?;
+            typed.verify(api, &eth.signature, &eth.signer)
+        },
+        _ => Err(WasmAuthError::generic("Only Ethereum credentials can sign typed data")),
+    }
+}
+
+
+
+/// Verify the typed data signed by the given credential against the expected envelope fields
+#[cfg(any(feature = "session", feature = "signed", feature = "ibc"))]
+pub(crate) fn verify_signed_typed(
+    api: &dyn Api,
+    env: &Env,
+    typed: &TypedDataToSign,
+    signed: &SignedDataMsg,
+    (id, name): (&str, &CredentialName),
+    nonce: u64,
+    messages: &[String],
+) -> Result<(), WasmAuthError> {
+    ensure!(
+        *name == CredentialName::EthPersonalSign,
+        WasmAuthError::generic("Only Ethereum credentials can sign typed data")
+    );
+    typed.check(env, nonce, Some(messages))?;
+    typed.verify(api, &signed.signature, id)
+}
+
+
+
+fn uint64(value: Uint64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.u64().to_be_bytes());
+    word
+}
+
+
+
+fn keccak(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    let mut output = [0u8; 32];
+    hasher.update(data);
+    hasher.finalize(&mut output);
+    output
+}
@@ -0,0 +1,260 @@
+use serde_cw_value::Value;
+use std::collections::BTreeMap;
+use types::{
+    envelope::ValidityBounds,
+    errors::{EnvelopeError, WasmAuthError}, 
+    wasm::{ensure, from_json, Env}
+};
+
+
+
+/// Compare the signed envelope with the expected one regardless of the order of the keys,
+/// whitespace or the encoding of the numbers (strings, fractions or exponents). The messages 
+/// are compared the same way if they are JSON themselves and only if given.
+/// The validity bounds of the envelope are checked against the current block
+pub(crate) fn match_envelope(
+    data: &[u8],
+    env: &Env,
+    nonce: u64,
+    messages: Option<&[String]>,
+) -> Result<(), WasmAuthError> {
+    let raw = envelope_map(&normalize_numbers(data)?)?;
+    bounds_of(&raw)?.check(&env.block)?;
+
+    let map = raw
+        .into_iter()
+        .map(|(k, v)| (canonical(k), canonical(v)))
+        .collect::<BTreeMap<Value, Value>>();
+
+    ensure!(
+        field(&map, "chain_id")? == Value::String(env.block.chain_id.clone()), 
+        EnvelopeError::ChainId
+    );
+    ensure!(
+        field(&map, "contract_address")? == Value::String(env.contract.address.to_string()), 
+        EnvelopeError::Contract
+    );
+    ensure!(
+        field(&map, "nonce")? == Value::String(nonce.to_string()), 
+        EnvelopeError::Nonce(nonce)
+    );
+
+    let messages = match messages {
+        Some(messages) => messages,
+        None => return Ok(()),
+    };
+    let signed = match field(&map, "messages")? {
+        Value::Seq(signed) => signed,
+        _ => return Err(EnvelopeError::Invalid("messages must be a list".to_string()).into()),
+    };
+    ensure!(
+        signed.len() == messages.len(), 
+        EnvelopeError::MessageCount(signed.len(), messages.len())
+    );
+    signed
+        .into_iter()
+        .zip(messages.iter())
+        .enumerate()
+        .try_for_each(|(i, (signed, expected))| {
+            ensure!(
+                canonical_msg(signed) == canonical_msg(Value::String(expected.clone())), 
+                EnvelopeError::Message(i)
+            );
+            Ok::<(), WasmAuthError>(())
+        })
+}
+
+
+
+/// Validity bounds of a signed envelope. Empty if the envelope doesn't have them 
+/// or if the data isn't a JSON object at all
+pub(crate) fn validity_bounds(
+    data: &[u8],
+) -> Result<ValidityBounds, EnvelopeError> {
+    match normalize_numbers(data).and_then(|data| envelope_map(&data)) {
+        Ok(map) => bounds_of(&map),
+        Err(_) => Ok(ValidityBounds::default()),
+    }
+}
+
+
+
+fn envelope_map(
+    data: &[u8],
+) -> Result<BTreeMap<Value, Value>, EnvelopeError> {
+    let value : Value = from_json(data)
+        .map_err(|e| EnvelopeError::Invalid(e.to_string()))?;
+    match value {
+        Value::Map(map) => Ok(map),
+        _ => Err(EnvelopeError::Invalid("not an object".to_string())),
+    }
+}
+
+
+
+fn bounds_of(
+    map: &BTreeMap<Value, Value>,
+) -> Result<ValidityBounds, EnvelopeError> {
+    let bound = |key: &str| match map.get(&Value::String(key.to_string())) {
+        Some(Value::Unit) | Some(Value::Option(None)) | None => Ok(None),
+        Some(value) => value
+            .clone()
+            .deserialize_into()
+            .map(Some)
+            .map_err(|e| EnvelopeError::Invalid(format!("{}: {}", key, e))),
+    };
+    Ok(ValidityBounds {
+        valid_after: bound("valid_after")?,
+        valid_until: bound("valid_until")?,
+    })
+}
+
+
+
+fn field(
+    map: &BTreeMap<Value, Value>,
+    key: &str,
+) -> Result<Value, EnvelopeError> {
+    map.get(&Value::String(key.to_string()))
+        .cloned()
+        .ok_or_else(|| EnvelopeError::Invalid(format!("missing field: {}", key)))
+}
+
+
+
+/// Messages encoded as JSON strings are parsed to be compared canonically
+fn canonical_msg(
+    value: Value
+) -> Value {
+    match value {
+        Value::String(text) => match normalize_numbers(text.as_bytes())
+            .ok()
+            .and_then(|json| from_json::<Value>(json).ok()) 
+        {
+            Some(parsed) => canonical(parsed),
+            None => Value::String(text),
+        },
+        other => canonical(other),
+    }
+}
+
+
+
+/// Keys of the maps are already sorted. Numbers are turned into strings and the wrappers are removed
+fn canonical(
+    value: Value
+) -> Value {
+    match value {
+        Value::U8(n) => Value::String(n.to_string()),
+        Value::U16(n) => Value::String(n.to_string()),
+        Value::U32(n) => Value::String(n.to_string()),
+        Value::U64(n) => Value::String(n.to_string()),
+        Value::I8(n) => Value::String(n.to_string()),
+        Value::I16(n) => Value::String(n.to_string()),
+        Value::I32(n) => Value::String(n.to_string()),
+        Value::I64(n) => Value::String(n.to_string()),
+        Value::Option(Some(inner)) | Value::Newtype(inner) => canonical(*inner),
+        Value::Option(None) => Value::Unit,
+        Value::Seq(items) => Value::Seq(items.into_iter().map(canonical).collect()),
+        Value::Map(map) => Value::Map(
+            map.into_iter()
+                .map(|(k, v)| (canonical(k), canonical(v)))
+                .collect()
+        ),
+        other => other,
+    }
+}
+
+
+
+/// Rewrite the numbers of the JSON in their shortest decimal form so that `1`, `1.0` and `1e0` are 
+/// all the same. Done on the text as the contracts can't parse the fractions as floats. The numbers
+/// that aren't 64-bit integers are put into strings
+fn normalize_numbers(
+    json: &[u8],
+) -> Result<Vec<u8>, EnvelopeError> {
+    let mut out = Vec::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut i = 0;
+
+    while i < json.len() {
+        let byte = json[i];
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+        } else if byte == b'-' || byte.is_ascii_digit() {
+            let end = json[i..]
+                .iter()
+                .position(|b| !matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+                .map_or(json.len(), |len| i + len);
+            let number = decimal(&json[i..end])?;
+            if number.parse::<i64>().is_ok() || number.parse::<u64>().is_ok() {
+                out.extend(number.into_bytes());
+            } else {
+                out.extend(format!("\"{}\"", number).into_bytes());
+            }
+            i = end;
+            continue;
+        } else if byte == b'"' {
+            in_string = true;
+        }
+        out.push(byte);
+        i += 1;
+    }
+    Ok(out)
+}
+
+
+
+/// Shortest decimal form of a JSON number without the leading and the trailing zeros
+fn decimal(
+    number: &[u8],
+) -> Result<String, EnvelopeError> {
+    let invalid = || EnvelopeError::Invalid(format!("number: {}", String::from_utf8_lossy(number)));
+    let text = std::str::from_utf8(number).map_err(|_| invalid())?;
+
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(pos) => (&text[..pos], text[pos + 1..].parse::<i64>().map_err(|_| invalid())?),
+        None => (text, 0),
+    };
+    // big exponents would only be used to blow up the size of the normalized data
+    ensure!(exponent.abs() <= 64, invalid());
+
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    ensure!(
+        !int.is_empty() && int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit()), 
+        invalid()
+    );
+    let digits = format!("{}{}", int, frac);
+    let point = int.len() as i64 + exponent;
+
+    let leading = digits.len() - digits.trim_start_matches('0').len();
+    let digits = &digits[leading..];
+    let point = point - leading as i64;
+    if digits.is_empty() {
+        return Ok("0".to_string());
+    }
+
+    let len = digits.len() as i64;
+    let value = if point >= len {
+        format!("{}{}", digits, "0".repeat((point - len) as usize))
+    } else if point <= 0 {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    } else {
+        format!("{}.{}", &digits[..point as usize], &digits[point as usize..])
+    };
+    let value = match value.contains('.') {
+        true => value.trim_end_matches('0').trim_end_matches('.').to_string(),
+        false => value,
+    };
+    Ok(if negative { format!("-{}", value) } else { value })
+}
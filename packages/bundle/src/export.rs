@@ -0,0 +1,141 @@
+use types::{
+    errors::{WasmAuthError, RestoreError},
+    export::{AuthState, AuthStateExport},
+    stores::{ACCOUNT_NUMBER, CREDENTIAL_INFOS as CREDS, HAS_NATIVES, PRIMARY_ID},
+    wasm::{ensure, Env, Storage},
+};
+#[cfg(feature = "session")]
+use {
+    std::collections::BTreeMap,
+    smart_account_auth::Session,
+    types::{
+        sessions::policy::SessionPolicy,
+        stores::{
+            get_map_records, SESSIONS, SESSION_CHILDREN, SESSION_CONFIG, SESSION_LABELS, SESSION_PARENTS, SESSION_POLICIES
+        },
+        wasm::Empty
+    },
+    crate::{policy::save_policy, session::{save_label, save_session}},
+};
+use crate::{commitment::update_credentials_root, get_stored_credentials};
+
+
+
+/// Export the credentials, the sessions and their settings to move them into another contract
+pub fn export_auth_state(
+    storage: &dyn Storage,
+    env: &Env,
+) -> Result<AuthStateExport, WasmAuthError> {
+    let state = AuthState {
+        account: env.contract.address.to_string(),
+        credentials: get_stored_credentials(storage)?,
+        #[cfg(feature = "session")]
+        session_config: SESSION_CONFIG.may_load(storage)?,
+        #[cfg(feature = "session")]
+        session_policies: get_map_records(storage, &SESSION_POLICIES, "session policies")?,
+        #[cfg(feature = "session")]
+        session_parents: get_map_records(storage, &SESSION_PARENTS, "session parents")?,
+        #[cfg(feature = "session")]
+        session_labels: get_map_records(storage, &SESSION_LABELS, "session labels")?,
+    };
+    let export = AuthStateExport::new(state)?;
+    Ok(export)
+}
+
+
+
+/// Import the state exported by `export_auth_state` into a contract without credentials.
+/// The contract must pass the account it's migrating from as `expected_account` from a trusted 
+/// source, e.g. an address fixed at its instantiation, since the hash only guards the integrity 
+/// of the state and anyone can export a state of their own. Both the sender and the account 
+/// recorded in the state must be the expected one.
+/// The account number and the nonces of the sessions continue from the exported ones and never go 
+/// below the counters that the new address already has, so the envelopes signed for the new address 
+/// before the restore, e.g. with a zero nonce, can't be used. Sessions granted by the old account
+/// are granted by the new one. Expired sessions and the sub-sessions of the dropped ones are not restored
+pub fn restore_credentials(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &str,
+    expected_account: &str,
+    export: AuthStateExport,
+) -> Result<(), WasmAuthError> {
+    ensure!(export.is_intact(), RestoreError::HashMismatch);
+    let old_account = export.state.account.as_str();
+    ensure!(sender == expected_account && old_account == expected_account, RestoreError::Unauthorized);
+    ensure!(env.contract.address.as_str() != old_account, RestoreError::SameAccount);
+    ensure!(!PRIMARY_ID.exists(storage), RestoreError::AlreadyInitialized);
+
+    let state = export.state;
+    let creds = &state.credentials;
+    ensure!(
+        creds.records.iter().any(|(id, _)| *id == creds.primary_id),
+        RestoreError::Invalid("primary credential is missing".to_string())
+    );
+
+    creds.records
+        .iter()
+        .try_for_each(|(id, info)| CREDS.save(storage, id.clone(), info))?;
+    PRIMARY_ID.save(storage, &creds.primary_id)?;
+    HAS_NATIVES.save(storage, &creds.has_natives)?;
+    let account_number = ACCOUNT_NUMBER.may_load(storage)?.unwrap_or(0).max(creds.account_number);
+    ACCOUNT_NUMBER.save(storage, &account_number)?;
+    update_credentials_root(storage)?;
+
+    #[cfg(feature = "session")]
+    restore_sessions(storage, env, state)?;
+    Ok(())
+}
+
+
+
+#[cfg(feature = "session")]
+fn restore_sessions(
+    storage: &mut dyn Storage,
+    env: &Env,
+    state: AuthState,
+) -> Result<(), WasmAuthError> {
+    if let Some(config) = &state.session_config {
+        SESSION_CONFIG.save(storage, config)?;
+    }
+    let parents = state.session_parents.iter().cloned().collect::<BTreeMap<String, String>>();
+    let policies = state.session_policies.into_iter().collect::<BTreeMap<String, SessionPolicy>>();
+    let labels = state.session_labels.into_iter().collect::<BTreeMap<String, String>>();
+    let live = state.credentials.sessions
+        .into_iter()
+        .filter(|(_, s)| !s.expiration.is_expired(&env.block))
+        .collect::<BTreeMap<String, Session>>();
+
+    for (key, session) in live.iter() {
+        // drop the sub-sessions whose parents haven't been kept
+        let mut ancestor = parents.get(key);
+        let mut depth = 0;
+        while let Some(parent) = ancestor {
+            if !live.contains_key(parent) {
+                break;
+            }
+            depth += 1;
+            ensure!(depth <= live.len(), RestoreError::Invalid("sessions have cyclic parents".to_string()));
+            ancestor = parents.get(parent);
+        }
+        if ancestor.is_some() {
+            continue;
+        }
+        let granter = match session.granter == state.account {
+            true => env.contract.address.to_string(),
+            false => session.granter.clone(),
+        };
+        let nonce = SESSIONS.may_load(storage, key.clone())?.map_or(0, |s| s.nonce).max(session.nonce);
+        let session = Session { nonce, granter, ..session.clone() };
+        save_session(storage, key, &session)?;
+        save_label(storage, key, &session, labels.get(key))?;
+        if let Some(policy) = policies.get(key) {
+            save_policy(storage, key, Some(policy.clone()))?;
+        }
+        if let Some(parent) = parents.get(key) {
+            SESSION_PARENTS.save(storage, key.clone(), parent)?;
+            SESSION_CHILDREN.save(storage, (parent.clone(), key.clone()), &Empty {})?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use types::{
+    errors::{WasmAuthError, IbcAuthError, StorageError},
+    ibc::{SignedActionsAck, SignedActionsPacket},
+    serde::{de::DeserializeOwned, Serialize},
+    stores::IBC_CHANNELS,
+    wasm::{ensure, from_json, to_json_string, DepsMut, Empty, Env, IbcPacketReceiveMsg, IbcReceiveResponse, Storage},
+};
+use crate::{utils::cred_from_signed, verify_cred_actions};
+#[cfg(feature = "eip712")]
+use {types::stores::ACCOUNT_NUMBER, crate::{account_number, utils::signer_info}};
+
+
+
+/// Accept the packets with signed actions coming through the given channel
+pub fn allow_ibc_channel(
+    storage: &mut dyn Storage,
+    channel_id: impl ToString,
+) -> Result<(), StorageError> {
+    IBC_CHANNELS.save(storage, channel_id.to_string(), &Empty {})
+        .map_err(|e| StorageError::Write("ibc channel".to_string(), e.to_string()))
+}
+
+
+/// Stop accepting the packets with signed actions coming through the given channel
+pub fn disallow_ibc_channel(
+    storage: &mut dyn Storage,
+    channel_id: impl ToString,
+) {
+    IBC_CHANNELS.remove(storage, channel_id.to_string());
+}
+
+
+pub fn is_ibc_channel_allowed(
+    storage: &dyn Storage,
+    channel_id: &str,
+) -> bool {
+    IBC_CHANNELS.has(storage, channel_id.to_string())
+}
+
+
+
+/// Verify the actions of a received packet and return the messages to execute.
+/// The envelope is checked against the current environment so it must have been signed 
+/// for the chain of the account and not for the one that has sent the packet
+pub fn verify_ibc_packet<M>(
+    deps: &mut DepsMut,
+    env: &Env,
+    msg: &IbcPacketReceiveMsg,
+) -> Result<Vec<M>, WasmAuthError> 
+    where M: Serialize + DeserializeOwned
+{
+    let channel = &msg.packet.dest.channel_id;
+    ensure!(
+        is_ibc_channel_allowed(deps.storage, channel),
+        IbcAuthError::ChannelNotAllowed(channel.clone())
+    );
+
+    let packet : SignedActionsPacket<M> = from_json(&msg.packet.data)
+        .map_err(|e| IbcAuthError::InvalidPacket(e.to_string()))?;
+    ensure!(!packet.msgs.is_empty(), IbcAuthError::InvalidPacket("no messages".to_string()));
+
+    let messages = packet.msgs
+        .iter()
+        .map(to_json_string)
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| IbcAuthError::InvalidPacket(e.to_string()))?;
+
+    // typed data can't be verified by the credential itself so it is checked on its own
+    #[cfg(feature = "eip712")]
+    if let Some(typed) = crate::eip712::TypedDataToSign::from_data(&packet.signed.data) {
+        let (id, info, _) = signer_info(deps.storage, &packet.signed)?;
+        let nonce = account_number(deps.storage);
+        crate::eip712::verify_signed_typed(
+            deps.api, env, &typed, &packet.signed, (&id, &info.name), nonce, &messages
+        )?;
+        ACCOUNT_NUMBER.save(deps.storage, &(nonce + 1))?;
+        return Ok(packet.msgs);
+    }
+    let cred = cred_from_signed(deps.as_ref(), packet.signed)?;
+    verify_cred_actions(deps.api, deps.storage, env, cred, Some(messages))?;
+    Ok(packet.msgs)
+}
+
+
+
+/// Response to a received packet with a successful or a failed acknowledgement.
+/// Errors are acknowledged instead of being returned so that the sender can handle them
+pub fn ibc_receive_response<T>(
+    result: Result<T, impl ToString>,
+) -> IbcReceiveResponse {
+    let ack = match result {
+        Ok(_) => SignedActionsAck::success(),
+        Err(err) => SignedActionsAck::error(err),
+    };
+    let success = ack.is_success().to_string();
+
+    #[cfg(feature = "cosmwasm")]
+    let res = IbcReceiveResponse::new(ack.to_binary());
+    #[cfg(not(feature = "cosmwasm"))]
+    let res = IbcReceiveResponse::new().set_ack(ack.to_binary());
+
+    res.add_attribute("action", "ibc_signed_actions")
+       .add_attribute("success", success)
+}
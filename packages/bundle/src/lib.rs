@@ -1,23 +1,67 @@
 #[cfg(feature = "session")]
 mod session;
+#[cfg(feature = "session")]
+mod policy;
+mod commitment;
+mod envelope;
+mod export;
+#[cfg(feature = "eip712")]
+mod eip712;
+#[cfg(feature = "ibc")]
+mod ibc;
+mod registry;
 mod utils;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 #[cfg(feature = "types")]
 pub use {types::stores, smart_account_auth as saa_types};
 #[cfg(feature = "utils")]
 pub use utils::*;
+#[cfg(feature = "eip712")]
+pub use eip712::{
+    typed_credential, verify_typed_credential, Eip712Domain, TypedBounds, TypedDataToSign, TypedMsgData,
+    EIP712_DOMAIN_NAME, EIP712_DOMAIN_VERSION
+};
+
+#[cfg(feature = "ibc")]
+pub use {
+    ibc::{
+        allow_ibc_channel, disallow_ibc_channel, ibc_receive_response, 
+        is_ibc_channel_allowed, verify_ibc_packet
+    },
+    types::ibc::{SignedActionsAck, SignedActionsPacket},
+};
 
 
 #[cfg(feature = "session")]
 pub use {
-    session::{handle_session_action, handle_session_query},
+    session::{
+        handle_session_action, handle_session_query, prune_expired_sessions, 
+        save_session_config, session_config, verify_session_native, verify_session_signed
+    },
+    policy::inner_cosmos_msgs,
     types::{
         macros::{session_query, session_action},
-        sessions::{queries::*, actions::*}
+        sessions::{queries::*, actions::*, policy::*, config::*, events::*}
     }
 };
 
+pub use commitment::{credential_proof, credentials_root, handle_credentials_query, update_credentials_root};
+
+pub use export::{export_auth_state, restore_credentials};
+
+pub use registry::{
+    predict_account_address, predicted_envelope, save_credentials_via_registry, 
+    verify_credentials_via_registry, RegistryContext
+};
+
 pub use types::{
+    adr036::{unwrap_adr036, wrap_adr036, StdSignDoc},
+    envelope::{BlockBound, ValidityBounds},
+    errors::WasmAuthError,
+    export::{AuthState, AuthStateExport},
+    merkle::{verify_credential_proof, CredentialProof, CredentialsQueryMsg},
     StoredCredentials, UpdateOperation
 };
 
@@ -30,7 +74,14 @@ use smart_account_auth::{
 use types::{
     stores::{ACCOUNT_NUMBER, HAS_NATIVES, PRIMARY_ID, CREDENTIAL_INFOS as CREDS}, 
     errors::{AuthError, CredentialError, ReplayError, StorageError}, 
-    wasm::{ensure, Env, Storage}, 
+    wasm::{ensure, Api, Env, Storage}, 
+};
+
+#[cfg(feature = "signed")]
+use {
+    core::fmt::Display,
+    smart_account_auth::msgs::SignedDataMsg,
+    types::{serde::Serialize, wasm::{Deps, DepsMut}},
 };
 
 
@@ -59,7 +110,7 @@ pub fn verify_native(
 pub fn verify_data(
     deps: Deps,
     msg: SignedDataMsg
-) -> Result<(), AuthError> {
+) -> Result<(), WasmAuthError> {
     utils::cred_from_signed(deps, msg)?;
     Ok(())
 } 
@@ -72,14 +123,12 @@ pub fn verify_signed<T : Serialize + Display + Clone>(
     env: &Env,
     messages: Vec<T>,
     signed: SignedDataMsg
-) -> Result<(), AuthError> {
+) -> Result<(), WasmAuthError> {
     let msgs = messages.iter()
         .map(|m| m.to_string())
         .collect::<Vec<String>>();
     let nonce = account_number(deps.storage);
-    let cred = utils::cred_from_signed(deps, signed)?;
-    cred.protect_reply(env, ReplayParams::new(nonce, CheckOption::Messages(msgs)))?;
-    Ok(())
+    verify_signed_msgs(deps, env, nonce, msgs, signed)
 }
 
 
@@ -89,26 +138,71 @@ pub fn verify_signed_actions<T : Serialize + Display + Clone>(
     env: &Env,
     messages: Vec<T>,
     signed: SignedDataMsg
-) -> Result<(), AuthError> {
+) -> Result<(), WasmAuthError> {
     let msgs = messages.iter()
         .map(|m| m.to_string())
         .collect::<Vec<String>>();
     let nonce = account_number(deps.storage);
-    let cred = utils::cred_from_signed(deps.as_ref(), signed)?;
-    cred.protect_reply(env, ReplayParams::new(nonce, CheckOption::Messages(msgs)))?;
+    verify_signed_msgs(deps.as_ref(), env, nonce, msgs, signed)?;
     ACCOUNT_NUMBER.save(deps.storage, &(nonce + 1))?;
     Ok(())
 }
 
 
 
+/// Verify the signed envelope encoded either as JSON or as EIP-712 typed data
+#[cfg(feature = "signed")]
+fn verify_signed_msgs(
+    deps: Deps,
+    env: &Env,
+    nonce: u64,
+    msgs: Vec<String>,
+    signed: SignedDataMsg
+) -> Result<(), WasmAuthError> {
+    #[cfg(feature = "eip712")]
+    if let Some(typed) = eip712::TypedDataToSign::from_data(&signed.data) {
+        let (id, info, _) = utils::signer_info(deps.storage, &signed)?;
+        return eip712::verify_signed_typed(
+            deps.api, env, &typed, &signed, (&id, &info.name), nonce, &msgs
+        );
+    }
+    let bounds = envelope::validity_bounds(&signed.data)?;
+    if !bounds.is_empty() {
+        envelope::match_envelope(&signed.data, env, nonce, Some(msgs.as_slice()))?;
+        utils::cred_from_signed(deps, signed)?;
+        return Ok(());
+    }
+    let cred = utils::cred_from_signed(deps, signed)?;
+    cred.protect_reply(env, ReplayParams::new(nonce, CheckOption::Messages(msgs)))?;
+    Ok(())
+}
+
+
+
+/// The `Api` checks the signatures of typed data envelopes with the `eip712` feature
 pub fn verify_cred_query(
+    api: &dyn Api,
     storage: &dyn Storage,
     env: &Env,
     cred: Credential,
     messages: Option<Vec<String>>
-) -> Result<u64, AuthError> {
+) -> Result<u64, WasmAuthError> {
+    #[cfg(not(feature = "eip712"))]
+    let _ = api;
     let nonce = account_number(storage);
+    // typed data can't be verified by the credential itself so its signature is checked here
+    #[cfg(feature = "eip712")]
+    if let Some(typed) = eip712::typed_credential(&cred) {
+        typed.check(env, nonce, messages.as_deref())?;
+        eip712::verify_typed_credential(api, &cred)?;
+        return Ok(nonce + 1);
+    }
+    // envelopes with validity bounds have extra fields so they are matched here
+    let message = cred.message();
+    if !envelope::validity_bounds(&message)?.is_empty() {
+        envelope::match_envelope(&message, env, nonce, messages.as_deref())?;
+        return Ok(nonce + 1);
+    }
     let check_option = match messages {
         Some(msgs) => CheckOption::Messages(msgs),
         None => CheckOption::Nothing,
@@ -120,12 +214,13 @@ pub fn verify_cred_query(
 
 
 pub fn verify_cred_actions(
+    api: &dyn Api,
     storage: &mut dyn Storage,
     env: &Env,
     cred: Credential,
     messages: Option<Vec<String>>
-) -> Result<(), AuthError> {
-    let new_nonce = &verify_cred_query(storage, env, cred, messages)?;
+) -> Result<(), WasmAuthError> {
+    let new_nonce = &verify_cred_query(api, storage, env, cred, messages)?;
     ACCOUNT_NUMBER.save(storage, new_nonce)?;
     Ok(())
 }
@@ -160,7 +255,8 @@ pub fn save_credentials(
         .iter()
         .try_for_each(|(id, info)| 
             CREDS.save(storage, id.clone(), info))
-        .map_err(|e| StorageError::Write("credentials".to_string(), e.to_string()))
+        .map_err(|e| StorageError::Write("credentials".to_string(), e.to_string()))?;
+    commitment::update_credentials_root(storage)
 }
 
 
@@ -193,10 +289,20 @@ pub fn reset_credentials(
     PRIMARY_ID.remove(storage);
     HAS_NATIVES.remove(storage);
     CREDS.clear(storage);
+    types::stores::CREDENTIALS_ROOT.remove(storage);
     if acc_number { ACCOUNT_NUMBER.remove(storage); }
     #[cfg(feature = "session")]
     if sessions {
         types::stores::SESSIONS.clear(storage);
+        types::stores::SESSION_POLICIES.clear(storage);
+        types::stores::SESSION_SPENDING.clear(storage);
+        types::stores::SESSION_USAGE.clear(storage);
+        types::stores::SESSION_EXPIRY_HEIGHTS.clear(storage);
+        types::stores::SESSION_EXPIRY_TIMES.clear(storage);
+        types::stores::SESSION_PARENTS.clear(storage);
+        types::stores::SESSION_CHILDREN.clear(storage);
+        types::stores::SESSION_LABELS.clear(storage);
+        types::stores::LABELED_SESSIONS.clear(storage);
     }
     Ok(())
 }
@@ -209,7 +315,7 @@ pub fn update_credentials(
 ) -> Result<(), AuthError> {
     match op {
         UpdateOperation::Add(data) => {
-            add_credentials(storage, &data)?;
+            add_credentials(storage, data)?;
         },
         UpdateOperation::Remove(idx) => {
             remove_credentials(storage, idx)?;
@@ -233,25 +339,25 @@ pub fn add_credentials(
     data.credentials
         .iter()
         .try_for_each(|(id, info)| {
-            CREDS.save(storage, id.clone(), &info)
+            CREDS.save(storage, id.clone(), info)
             .map_err(|e| AuthError::Storage(
                 StorageError::Write(id.to_string(), e.to_string())
             ))
         }
-    )
+    )?;
+    commitment::update_credentials_root(storage)?;
+    Ok(())
 }
 
 
 
 pub fn remove_credentials(
     storage: &mut dyn Storage,
-    idx: &Vec<CredentialId>,
+    idx: &[CredentialId],
 ) -> Result<Vec<CredentialRecord>, AuthError> {
     ensure!(!idx.is_empty(), CredentialError::NoCredentials);
 
-    let idx = idx.iter()
-        .map(|id| id.clone())
-        .collect::<Vec<_>>();
+    let idx = idx.to_vec();
     
     let all_creds = utils::get_credentials(storage)?;
     let had_natives = HAS_NATIVES.load(storage)?;
@@ -298,6 +404,7 @@ pub fn remove_credentials(
         }
     }
 
+    commitment::update_credentials_root(storage)?;
     Ok(remaining)
 }
 
@@ -0,0 +1,437 @@
+use serde_cw_value::Value;
+use types::{
+    errors::{WasmAuthError, SessionPolicyError, StorageError},
+    serde::Serialize,
+    sessions::{
+        actions::MsgArg,
+        policy::{ArgConstraint, BlockMark, ConstraintRule, CosmosMsgPolicy, DenomSpending, RateLimit, SessionPolicy, SpendingLimit},
+    },
+    stores::{map_remove, map_save, SESSION_POLICIES, SESSION_SPENDING, SESSION_USAGE},
+    wasm::{ensure, from_json, to_json_vec, Coin, CosmosMsg, Env, MessageInfo, Storage, Uint128, WasmMsg},
+};
+
+
+
+/// Keys of the JSON objects that represent a serialized `CosmosMsg`
+const COSMOS_MSG_KEYS : [&str; 9] = [
+    "bank", "custom", "staking", "distribution", "stargate", "any", "ibc", "wasm", "gov"
+];
+
+
+
+
+/// Every `CosmosMsg` found at any depth inside of the given message
+pub fn inner_cosmos_msgs<M: Serialize>(
+    msg: &M
+) -> Result<Vec<CosmosMsg>, WasmAuthError> {
+    Ok(kinded_cosmos_msgs(msg)?
+        .into_iter()
+        .map(|(_, msg)| msg)
+        .collect())
+}
+
+
+
+/// Found `CosmosMsg`s together with their kind like `bank.send` or `wasm.execute`
+fn kinded_cosmos_msgs<M: Serialize>(
+    msg: &M
+) -> Result<Vec<(String, CosmosMsg)>, WasmAuthError> {
+    let value : Value = from_json(to_json_vec(msg)?)?;
+    let mut found = vec![];
+    collect_cosmos_msgs(value, &mut found);
+    Ok(found)
+}
+
+
+
+fn collect_cosmos_msgs(
+    value: Value,
+    found: &mut Vec<(String, CosmosMsg)>
+) {
+    match value {
+        Value::Map(map) => {
+            let tagged = map.len() == 1 && map.keys().all(|k| matches!(
+                k, Value::String(s) if COSMOS_MSG_KEYS.contains(&s.as_str())
+            ));
+            if tagged {
+                let kind = msg_kind(&map);
+                if let Ok(msg) = Value::Map(map.clone()).deserialize_into::<CosmosMsg>() {
+                    found.push((kind, msg));
+                    return;
+                }
+            }
+            map.into_values().for_each(|v| collect_cosmos_msgs(v, found));
+        },
+        Value::Seq(seq) => seq.into_iter().for_each(|v| collect_cosmos_msgs(v, found)),
+        Value::Option(Some(inner)) | Value::Newtype(inner) => collect_cosmos_msgs(*inner, found),
+        _ => {}
+    }
+}
+
+
+
+fn msg_kind(map: &std::collections::BTreeMap<Value, Value>) -> String {
+    map.iter()
+        .map(|(key, inner)| {
+            let key = match key {
+                Value::String(key) => key.clone(),
+                _ => String::default(),
+            };
+            match inner {
+                Value::Map(inner) if inner.len() == 1 => match inner.keys().next() {
+                    Some(Value::String(name)) => format!("{}.{}", key, name),
+                    _ => key,
+                },
+                _ => key,
+            }
+        })
+        .next()
+        .unwrap_or_default()
+}
+
+
+
+/// Kinds of the messages that can't move the funds of the account
+const UNFUNDED_KINDS : [&str; 11] = [
+    "wasm.migrate", "wasm.update_admin", "wasm.clear_admin", "staking.undelegate", "staking.redelegate", 
+    "distribution.withdraw_delegator_reward", "gov.vote", "gov.vote_weighted", "ibc.send_packet", 
+    "ibc.write_acknowledgement", "ibc.close_channel",
+];
+
+
+/// Coins that leave the account when the message is executed. Messages that might move 
+/// the funds in a way that can't be metered, e.g. `stargate` or `any`, are rejected
+fn sent_funds(
+    kind: &str, 
+    msg: &CosmosMsg
+) -> Result<Vec<Coin>, WasmAuthError> {
+    let field = match kind {
+        "bank.send" | "bank.burn" | "distribution.fund_community_pool" => "amount",
+        "wasm.execute" | "wasm.instantiate" | "wasm.instantiate2" => "funds",
+        "ibc.transfer" | "staking.delegate" => "amount",
+        kind if UNFUNDED_KINDS.contains(&kind) => return Ok(vec![]),
+        kind => return Err(SessionPolicyError::UnmeteredMsg(kind.to_string()).into()),
+    };
+    let value : Value = from_json(to_json_vec(msg)?)?;
+    let funds = kind
+        .split('.')
+        .chain(std::iter::once(field))
+        .try_fold(&value, |value, key| match unwrapped(value) {
+            Value::Map(map) => map.get(&Value::String(key.to_string())),
+            _ => None,
+        })
+        .ok_or_else(|| SessionPolicyError::UnmeteredMsg(kind.to_string()))?;
+    let coins = match unwrapped(funds) {
+        Value::Seq(_) => funds.clone().deserialize_into::<Vec<Coin>>(),
+        _ => funds.clone().deserialize_into::<Coin>().map(|coin| vec![coin]),
+    }
+    .map_err(|_| SessionPolicyError::UnmeteredMsg(kind.to_string()))?;
+    Ok(coins)
+}
+
+
+
+
+pub(crate) fn save_policy(
+    storage: &mut dyn Storage,
+    key: &str,
+    policy: Option<SessionPolicy>,
+) -> Result<(), StorageError> {
+    remove_policy(storage, key);
+    match policy {
+        Some(policy) if !policy.is_empty() => {
+            map_save(storage, &SESSION_POLICIES, key, &policy, "session policy")
+        },
+        _ => Ok(())
+    }
+}
+
+
+pub(crate) fn remove_policy(
+    storage: &mut dyn Storage,
+    key: &str,
+) {
+    map_remove(storage, &SESSION_POLICIES, key);
+    map_remove(storage, &SESSION_SPENDING, key);
+    map_remove(storage, &SESSION_USAGE, key);
+}
+
+
+
+
+/// Check the message of a session key against the rules of the attached policy 
+/// that only depend on the message itself
+pub(crate) fn check_session_rules<M: Serialize>(
+    storage: &dyn Storage,
+    key: &str,
+    msgs: &MsgArg<M>,
+) -> Result<(), WasmAuthError> {
+    let policy = match SESSION_POLICIES.may_load(storage, key.to_string())? {
+        Some(policy) => policy,
+        None => return Ok(()),
+    };
+    if !policy.constraints.is_empty() {
+        #[cfg(not(feature = "multi"))]
+        check_constraints(&policy.constraints, msgs)?;
+        #[cfg(feature = "multi")]
+        msgs.iter().try_for_each(|m| check_constraints(&policy.constraints, m))?;
+    }
+    if let Some(cosmos_policy) = &policy.cosmos_msgs {
+        check_cosmos_msgs(cosmos_policy, msgs)?;
+    }
+    Ok(())
+}
+
+
+
+fn check_cosmos_msgs<M: Serialize>(
+    policy: &CosmosMsgPolicy,
+    msg: &M,
+) -> Result<(), WasmAuthError> {
+    for (kind, msg) in kinded_cosmos_msgs(msg)? {
+        ensure!(!policy.forbids(&kind), SessionPolicyError::ForbiddenMsg(kind));
+
+        let contract = match &msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) |
+            CosmosMsg::Wasm(WasmMsg::Migrate { contract_addr, .. }) |
+            CosmosMsg::Wasm(WasmMsg::UpdateAdmin { contract_addr, .. }) |
+            CosmosMsg::Wasm(WasmMsg::ClearAdmin { contract_addr }) => Some(contract_addr),
+            _ => None,
+        };
+        if let (Some(contract), Some(allowed)) = (contract, &policy.allowed_contracts) {
+            ensure!(allowed.contains(contract), SessionPolicyError::ContractNotAllowed(contract.clone()));
+        }
+
+        if let CosmosMsg::Wasm(WasmMsg::Execute { msg: payload, .. }) = &msg {
+            let inner : Value = from_json(payload)?;
+            if let Some(allowed) = &policy.allowed_execute_msgs {
+                let name = match &inner {
+                    Value::Map(map) => map.keys().next().and_then(|k| match k {
+                        Value::String(name) => Some(name.clone()),
+                        _ => None,
+                    }),
+                    Value::String(name) => Some(name.clone()),
+                    _ => None,
+                }
+                .unwrap_or_default();
+                ensure!(allowed.contains(&name), SessionPolicyError::ExecuteMsgNotAllowed(name));
+            }
+            // the contract being executed might dispatch the messages further e.g. another smart account
+            check_cosmos_msgs(policy, &inner)?;
+        }
+    }
+    Ok(())
+}
+
+
+
+fn check_constraints<M: Serialize>(
+    constraints: &[ArgConstraint],
+    msg: &M,
+) -> Result<(), WasmAuthError> {
+    let value : Value = from_json(to_json_vec(msg)?)?;
+
+    for constraint in constraints {
+        let mut segments = constraint.path.split('.');
+        let variant = Value::String(segments.next().unwrap_or_default().to_string());
+
+        let inner = match &value {
+            Value::Map(map) => match map.get(&variant) {
+                Some(inner) => inner,
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        let segments = segments.collect::<Vec<&str>>();
+        let allowed = match field_values(inner, &segments) {
+            // the total of all the matched amounts is what is being limited
+            Some(values) if matches!(constraint.rule, ConstraintRule::MaxAmount(_)) => values
+                .iter()
+                .try_fold(Uint128::zero(), |total, v| total.checked_add(v.parse::<Uint128>().ok()?).ok())
+                .is_some_and(|total| constraint.rule.allows(&total.to_string())),
+            Some(values) => values.iter().all(|v| constraint.rule.allows(v)),
+            None => false,
+        };
+
+        ensure!(allowed, SessionPolicyError::Constraint(constraint.path.clone()));
+    }
+    Ok(())
+}
+
+
+
+/// Values of the field under the path where `*` stands for every element of a list
+/// or every value of a map. None if any of the paths doesn't lead to a plain value
+fn field_values(
+    value: &Value,
+    segments: &[&str],
+) -> Option<Vec<String>> {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return scalar(value).map(|v| vec![v]),
+    };
+    match (unwrapped(value), *segment) {
+        (Value::Seq(seq), "*") => seq.iter()
+            .map(|el| field_values(el, rest))
+            .collect::<Option<Vec<_>>>()
+            .map(|all| all.concat()),
+        (Value::Map(map), "*") => map.values()
+            .map(|el| field_values(el, rest))
+            .collect::<Option<Vec<_>>>()
+            .map(|all| all.concat()),
+        (Value::Map(map), key) => field_values(map.get(&Value::String(key.to_string()))?, rest),
+        (Value::Seq(seq), idx) => field_values(seq.get(idx.parse::<usize>().ok()?)?, rest),
+        _ => None,
+    }
+}
+
+
+fn scalar(value: &Value) -> Option<String> {
+    match unwrapped(value) {
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::U8(n) => Some(n.to_string()),
+        Value::U16(n) => Some(n.to_string()),
+        Value::U32(n) => Some(n.to_string()),
+        Value::U64(n) => Some(n.to_string()),
+        Value::I8(n) => Some(n.to_string()),
+        Value::I16(n) => Some(n.to_string()),
+        Value::I32(n) => Some(n.to_string()),
+        Value::I64(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+
+fn unwrapped(value: &Value) -> &Value {
+    match value {
+        Value::Option(Some(inner)) | Value::Newtype(inner) => unwrapped(inner),
+        _ => value,
+    }
+}
+
+
+
+
+/// Check the message of a session key against the limits of the attached policy
+/// and record the usage
+pub(crate) fn check_session_policy<M: Serialize>(
+    storage: &mut dyn Storage,
+    env: &Env,
+    info: &MessageInfo,
+    key: &str,
+    msgs: &M,
+) -> Result<(), WasmAuthError> {
+    let policy = match SESSION_POLICIES.may_load(storage, key.to_string())? {
+        Some(policy) => policy,
+        None => return Ok(()),
+    };
+    if !policy.spending_limits.is_empty() {
+        let mut spent = info.funds.clone();
+        for (kind, msg) in kinded_cosmos_msgs(msgs)? {
+            spent.extend(sent_funds(&kind, &msg)?);
+        }
+        check_spending_limits(storage, env, key, &policy.spending_limits, &spent)?;
+    }
+    if let Some(rate_limit) = &policy.rate_limit {
+        check_rate_limit(storage, env, key, rate_limit)?;
+    }
+    Ok(())
+}
+
+
+
+/// Number of actions a rate limited session key can still perform in the current window
+pub(crate) fn remaining_actions(
+    storage: &dyn Storage,
+    env: &Env,
+    key: &str,
+    rate_limit: &RateLimit,
+) -> Result<u32, StorageError> {
+    let used = window_usage(storage, env, key, rate_limit)?.len() as u32;
+    Ok(rate_limit.max_actions.saturating_sub(used))
+}
+
+
+
+fn window_usage(
+    storage: &dyn Storage,
+    env: &Env,
+    key: &str,
+    rate_limit: &RateLimit,
+) -> Result<Vec<BlockMark>, StorageError> {
+    let usage = SESSION_USAGE
+        .may_load(storage, key.to_string())
+        .map_err(|e| StorageError::Read("session usage".to_string(), e.to_string()))?
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|mark| !rate_limit.window.elapsed(mark, &env.block))
+        .collect();
+    Ok(usage)
+}
+
+
+
+fn check_rate_limit(
+    storage: &mut dyn Storage,
+    env: &Env,
+    key: &str,
+    rate_limit: &RateLimit,
+) -> Result<(), WasmAuthError> {
+    let mut usage = window_usage(storage, env, key, rate_limit)?;
+    ensure!((usage.len() as u32) < rate_limit.max_actions, SessionPolicyError::RateLimit);
+    usage.push((&env.block).into());
+    map_save(storage, &SESSION_USAGE, key, &usage, "session usage")?;
+    Ok(())
+}
+
+
+
+fn check_spending_limits(
+    storage: &mut dyn Storage,
+    env: &Env,
+    key: &str,
+    limits: &[SpendingLimit],
+    spent: &[Coin],
+) -> Result<(), WasmAuthError> {
+    let mut records = SESSION_SPENDING
+        .may_load(storage, key.to_string())?
+        .unwrap_or_default();
+
+    for limit in limits {
+        let amount = spent
+            .iter()
+            .filter(|c| c.denom == limit.denom)
+            .try_fold(Uint128::zero(), |acc, c| acc.checked_add(c.amount))
+            .map_err(|_| SessionPolicyError::SpendingLimit(limit.denom.clone()))?;
+
+        if amount.is_zero() { continue; }
+
+        let mut record = match records.iter().position(|r| r.denom == limit.denom) {
+            Some(index) => records.remove(index),
+            None => DenomSpending::new(&limit.denom, &env.block),
+        };
+
+        record.total = record.total.checked_add(amount)
+            .map_err(|_| SessionPolicyError::SpendingLimit(limit.denom.clone()))?;
+
+        if let Some(total) = limit.total {
+            ensure!(record.total <= total, SessionPolicyError::SpendingLimit(limit.denom.clone()));
+        }
+
+        if let Some(per_period) = &limit.per_period {
+            if per_period.period.elapsed(&record.period_start, &env.block) {
+                record.period = Uint128::zero();
+                record.period_start = (&env.block).into();
+            }
+            record.period = record.period.checked_add(amount)
+                .map_err(|_| SessionPolicyError::SpendingLimit(limit.denom.clone()))?;
+            ensure!(record.period <= per_period.amount, SessionPolicyError::SpendingLimit(limit.denom.clone()));
+        }
+        records.push(record);
+    }
+
+    map_save(storage, &SESSION_SPENDING, key, &records, "session spending")?;
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use smart_account_auth::{msgs::MsgDataToSign, CredentialData, CredentialsWrapper, ReplayParams, VerifiedData};
+use types::{
+    errors::WasmAuthError, 
+    wasm::{instantiate2_address, Addr, Api, Deps, DepsMut, Env, MessageInfo}
+};
+use crate::save_credentials;
+
+
+/// Replay context of the credentials that have been signed for a registry / factory 
+/// instead of the account that is going to store them
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegistryContext {
+    /// Address of the registry used as the `contract_address` of the signed envelope
+    pub registry    :   Addr,
+    /// Sender of the original message to the registry. Used for the native credentials
+    pub sender      :   Addr,
+    /// Chain ID of the signed envelope if it differs from the one of the current chain
+    pub chain_id    :   Option<String>,
+}
+
+
+
+impl RegistryContext {
+
+    pub fn new(registry: Addr, sender: Addr) -> Self {
+        Self { registry, sender, chain_id: None }
+    }
+
+    pub fn with_chain_id(mut self, chain_id: impl Into<String>) -> Self {
+        self.chain_id = Some(chain_id.into());
+        self
+    }
+
+    /// Environment as it was seen by the signers of the envelope
+    pub fn env(&self, env: &Env) -> Env {
+        let mut env = env.clone();
+        env.contract.address = self.registry.clone();
+        if let Some(chain_id) = &self.chain_id {
+            env.block.chain_id = chain_id.clone();
+        }
+        env
+    }
+
+    /// Message info with the original sender
+    pub fn info(&self, info: &MessageInfo) -> MessageInfo {
+        let mut info = info.clone();
+        info.sender = self.sender.clone();
+        info
+    }
+}
+
+
+
+/// Verify the credential data signed for the registry without saving it
+pub fn verify_credentials_via_registry(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    context: &RegistryContext,
+    data: &CredentialData,
+    params: ReplayParams,
+) -> Result<VerifiedData, WasmAuthError> {
+    Ok(data.verify(deps, &context.env(env), &context.info(info), params)?)
+}
+
+
+
+/// Verify the credential data signed for the registry and save it to the storage of the account
+pub fn save_credentials_via_registry(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    context: &RegistryContext,
+    data: &CredentialData,
+    params: ReplayParams,
+) -> Result<VerifiedData, WasmAuthError> {
+    let verified = verify_credentials_via_registry(deps.as_ref(), env, info, context, data, params)?;
+    save_credentials(deps.storage, &verified)?;
+    Ok(verified)
+}
+
+
+
+/// Address that an account will have when instantiated with `instantiate2` by the creator
+pub fn predict_account_address(
+    api: &dyn Api,
+    checksum: &[u8],
+    creator: &str,
+    salt: &[u8],
+) -> Result<Addr, WasmAuthError> {
+    let creator = api.addr_canonicalize(creator)?;
+    let canonical = instantiate2_address(checksum, &creator, salt)
+        .map_err(|e| WasmAuthError::generic(e.to_string()))?;
+    Ok(api.addr_humanize(&canonical)?)
+}
+
+
+
+/// Envelope that the credentials have to sign before the account is instantiated with `instantiate2` 
+/// so that `save_credentials` accepts them. Accounts without stored credentials expect the nonce to be `0`
+pub fn predicted_envelope(
+    api: &dyn Api,
+    env: &Env,
+    checksum: &[u8],
+    creator: &str,
+    salt: &[u8],
+    messages: Vec<String>,
+) -> Result<MsgDataToSign, WasmAuthError> {
+    Ok(MsgDataToSign {
+        chain_id: env.block.chain_id.clone(),
+        contract_address: predict_account_address(api, checksum, creator, salt)?.to_string(),
+        messages,
+        nonce: 0u64.into(),
+    })
+}
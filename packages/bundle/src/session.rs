@@ -1,36 +1,75 @@
 use smart_account_auth::{
-    msgs::{Action, AllQueryDerivation, MsgDataToSign, SignedDataMsg}, 
-    Caller, Credential, DerivableMsg, Session
+    msgs::{Action, AllQueryDerivation, SignedDataMsg}, 
+    Caller, Credential, CredentialAddress, CredentialInfo, DerivableMsg, Expiration, Session
 };
 use types::{
-    errors::{AuthError, SessionError, StorageError}, serde::{self, Serialize}, sessions::{
-        actions::{MsgArg, SessionAction, SessionActionMsg}, 
-        queries::{QueryUsesActions, SessionQueryMsg}
-    }, stores::{get_map_records, map_get, map_remove, map_save, SESSIONS}, strum::{IntoDiscriminant, VariantArray, VariantNames}, wasm::{
-        ensure, to_json_binary, Api, Binary, 
-        Deps, DepsMut, Env, MessageInfo, Response, 
+    adr036::unwrap_adr036,
+    errors::{EnvelopeError, SessionError, StorageError, SubSessionError, WasmAuthError}, serde::{self, Serialize}, sessions::{
+        actions::{check_parent_bounds, labeled_key, CreationMode, MsgArg, RevokeSessionsMsg, SessionAction, SessionActionMsg}, 
+        queries::{QueryUsesActions, SessionQueryMsg, SessionResponse},
+        config::{ExpiredKeyPolicy, SessionAdmins, SessionConfig},
+        policy::SessionPolicy,
+        events::{
+            SessionCreatedEvent, SessionEvent, SessionExpiredEvent, 
+            SessionRevokedEvent, SessionUsedEvent
+        },
+    }, stores::{
+        expired_session_keys, get_map_records, map_get, map_remove, map_save, 
+        CREDENTIAL_INFOS, LABELED_SESSIONS, SESSIONS, SESSION_CHILDREN, SESSION_CONFIG, 
+        SESSION_EXPIRY_HEIGHTS, SESSION_EXPIRY_TIMES, SESSION_LABELS, SESSION_PARENTS, SESSION_POLICIES
+    }, strum::{IntoDiscriminant, VariantArray, VariantNames}, wasm::{
+        ensure, to_json_binary, to_json_string, Api, Binary, 
+        Deps, DepsMut, Empty, Env, Event, MessageInfo, Order, Response, 
         StdError, StdResult, Storage
     } 
 };
 
-use crate::{utils::session_cred_from_signed};
+use crate::{
+    envelope::match_envelope,
+    policy::{check_session_policy, check_session_rules, remaining_actions, remove_policy, save_policy}, 
+    utils::session_cred,
+    verify_native
+};
+#[cfg(feature = "signed")]
+use crate::verify_signed_actions;
 
 
 
-fn validate_common(
-    deps: Deps,
+/// Checks the actions together with the argument rules of the session and of all its parents
+fn validate_actions(
+    storage: &dyn Storage,
+    key: &str,
     session: &Session,
-    cred    : &Credential,
     msgs    : &MsgArg<impl DerivableMsg>
-) -> Result<(), AuthError> {
-    let (id, info) = session.grantee.clone();
-    ensure!(id == cred.id(), SessionError::NotGrantee);
-    let cred_info = cred.verify(deps)?;
-    ensure!(info == cred_info, SessionError::InvalidGrantee);
+) -> Result<(), WasmAuthError> {
     #[cfg(not(feature = "multi"))]
     ensure!(session.can_do_msg(msgs), SessionError::NotAllowedAction);
     #[cfg(feature = "multi")]
     ensure!(msgs.iter().all(|m| session.can_do_msg(m)), SessionError::NotAllowedAction);
+
+    check_session_rules(storage, key, msgs)?;
+    let mut current = key.to_string();
+    while let Some(parent) = SESSION_PARENTS.may_load(storage, current)? {
+        check_session_rules(storage, &parent, msgs)?;
+        current = parent;
+    }
+    Ok(())
+}
+
+
+/// The only place where the credential of a grantee is verified. Meant to be called 
+/// after the cheap checks so that the signature isn't verified for a call that would fail anyway
+fn validate_grantee(
+    deps: Deps,
+    session: &Session,
+    cred    : &Credential,
+) -> Result<(), WasmAuthError> {
+    let (id, info) = &session.grantee;
+    ensure!(*id == cred.id(), SessionError::NotGrantee);
+    cred.validate()?;
+    // the extension only holds what is needed for rebuilding the credential
+    let cred_info = CredentialInfo { extension: info.extension.clone(), ..crate::utils::verify_credential(deps, cred)? };
+    ensure!(*info == cred_info, SessionError::InvalidGrantee);
     Ok(())
 }
 
@@ -38,11 +77,22 @@ fn validate_common(
 pub fn verify_session_native(
     deps: Deps,
     address: &str,
+    key: &str,
     session: &Session,
     msgs    : &MsgArg<impl DerivableMsg>
-) -> Result<(), AuthError> {
+) -> Result<(), WasmAuthError> {
+    validate_actions(deps.storage, key, session, msgs)?;
+    verify_grantee_native(deps, address, session)
+}
+
+
+fn verify_grantee_native(
+    deps: Deps,
+    address: &str,
+    session: &Session,
+) -> Result<(), WasmAuthError> {
     let caller : Caller = address.into();
-    validate_common(deps, session, &caller.into(), msgs)
+    validate_grantee(deps, session, &caller.into())
 }
 
 
@@ -50,33 +100,86 @@ pub fn verify_session_native(
 pub fn verify_session_signed<T : Serialize + DerivableMsg>(
     deps: &mut DepsMut,
     env: &Env,
-    key: &String,
+    key: &str,
     session: &mut Session,
     msgs: MsgArg<T>,
     signed: SignedDataMsg
-) -> Result<(), AuthError> {
+) -> Result<(), WasmAuthError> {
     #[cfg(feature = "multi")]
     let messages = msgs.iter().map(|m|m.to_json_string()).collect::<Result<Vec<String>, _>>()?;
     #[cfg(not(feature = "multi"))]
     let messages = vec![msgs.to_json_string()?];
-    let nonce = session.nonce.clone();
-    let envelope = to_json_binary(&MsgDataToSign {
-        nonce: nonce.into(),
-        chain_id: env.block.chain_id.clone(),
-        contract_address: env.contract.address.to_string(),
-        messages,
-    })?;
-    ensure!(envelope == signed.data, AuthError::generic("Data mismatch in signed message"));
-    let deps_ref = deps.as_ref();
-    let cred = session_cred_from_signed(deps_ref,  key, signed)?;
-    validate_common(deps_ref, &session, &cred, &msgs)?;
+    validate_actions(deps.storage, key, session, &msgs)?;
+    verify_grantee_signed(deps, env, key, session, messages, signed)
+}
+
+
+
+fn verify_grantee_signed(
+    deps: &mut DepsMut,
+    env: &Env,
+    key: &str,
+    session: &mut Session,
+    messages: Vec<String>,
+    signed: SignedDataMsg
+) -> Result<(), WasmAuthError> {
+    let nonce = session.nonce;
+
+    #[cfg(feature = "eip712")]
+    match crate::eip712::TypedDataToSign::from_data(&signed.data) {
+        Some(typed) => {
+            let (id, info) = &session.grantee;
+            crate::eip712::verify_signed_typed(
+                deps.api, env, &typed, &signed, (id, &info.name), nonce, &messages
+            )?;
+        },
+        None => verify_grantee_json(deps.as_ref(), env, session, messages, signed)?,
+    }
+    #[cfg(not(feature = "eip712"))]
+    verify_grantee_json(deps.as_ref(), env, session, messages, signed)?;
+
     session.nonce = nonce + 1;
-    map_save(deps.storage, &SESSIONS, key, &session, "session key")?;
+    map_save(deps.storage, &SESSIONS, key, session, "session key")?;
     Ok(())
 }
 
 
 
+/// Address of the grantee that is expected to sign the ADR-036 sign docs
+fn grantee_address(
+    session: &Session
+) -> String {
+    let (id, info) = &session.grantee;
+    match &info.address {
+        Some(CredentialAddress::Bech32(address)) => address.to_string(),
+        _ => id.clone(),
+    }
+}
+
+
+
+fn verify_grantee_json(
+    deps: Deps,
+    env: &Env,
+    session: &Session,
+    messages: Vec<String>,
+    signed: SignedDataMsg
+) -> Result<(), WasmAuthError> {
+    // envelopes signed with `signArbitrary` of Cosmos wallets
+    let signed = match unwrap_adr036(&signed.data) {
+        Some((data, signer)) => {
+            ensure!(signer == grantee_address(session), EnvelopeError::Adr036Signer(signer));
+            SignedDataMsg { data, ..signed }
+        },
+        None => signed,
+    };
+    match_envelope(&signed.data, env, session.nonce, Some(messages.as_slice()))?;
+    let cred = session_cred(session, signed)?;
+    validate_grantee(deps, session, &cred)
+}
+
+
+
 pub fn get_session_records(
     storage: &dyn Storage
 ) -> Result<Vec<(String, Session)>, StorageError> {
@@ -88,19 +191,388 @@ pub fn get_session_records(
 
 pub fn update_session(
     storage: &mut dyn Storage,
-    key: &String,
+    key: &str,
     session: &Session,
 ) -> Result<u64, StorageError> {
     let session = match map_get(storage, &SESSIONS, key, "session key") {
         Ok(loaded) => &Session { nonce: loaded.nonce + 1, ..session.clone() },
         Err(_) => session,
     };
-    map_save(storage, &SESSIONS, &key, session, "session key")?;
+    save_session(storage, key, session)?;
     Ok(session.nonce)
 }
 
 
 
+/// Store a new session resolving a conflict with an existing one according to the creation mode
+fn create_session(
+    storage: &mut dyn Storage,
+    env: &Env,
+    key: &str,
+    session: Session,
+    mode: &CreationMode,
+    policy: Option<SessionPolicy>,
+) -> Result<u64, WasmAuthError> {
+    let existing = SESSIONS.may_load(storage, key.to_string())?;
+    let replaced = existing.is_some();
+    let merged = *mode == CreationMode::Merge && existing
+        .as_ref()
+        .is_some_and(|e| !e.expiration.is_expired(&env.block));
+    let session = mode.resolve(env, key, existing, session)?;
+
+    match merged {
+        // merged sessions keep their sub-sessions and the policy unless a new one is given 
+        true => {
+            if policy.is_some() {
+                save_policy(storage, key, policy)?;
+            }
+        },
+        false => {
+            if replaced {
+                remove_session(storage, key);
+            }
+            save_policy(storage, key, policy)?;
+        }
+    }
+    save_session(storage, key, &session)?;
+    Ok(session.nonce)
+}
+
+
+
+/// Key of the new session derived from its actions. Labeled sessions keep the key under which the same 
+/// granter has first given the label to the grantee so that they can be replaced or merged
+fn creation_key(
+    storage: &dyn Storage,
+    session: &Session,
+    label: Option<&String>,
+) -> Result<String, WasmAuthError> {
+    let key = labeled_key(session, label)?;
+    match label {
+        Some(label) => {
+            let index = (session.granter.clone(), session.grantee.0.clone(), label.clone());
+            Ok(LABELED_SESSIONS.may_load(storage, index)?.unwrap_or(key))
+        },
+        None => Ok(key),
+    }
+}
+
+
+
+pub(crate) fn save_label(
+    storage: &mut dyn Storage,
+    key: &str,
+    session: &Session,
+    label: Option<&String>,
+) -> Result<(), StorageError> {
+    if let Some(label) = label {
+        let index = (session.granter.clone(), session.grantee.0.clone(), label.clone());
+        LABELED_SESSIONS.save(storage, index, &key.to_string())
+            .map_err(|e| StorageError::Write("labeled session".to_string(), e.to_string()))?;
+        map_save(storage, &SESSION_LABELS, key, label, "session label")?;
+    }
+    Ok(())
+}
+
+
+
+/// Store the session as it is while keeping the expiration indexes in sync
+pub fn save_session(
+    storage: &mut dyn Storage,
+    key: &str,
+    session: &Session,
+) -> Result<(), StorageError> {
+    if let Ok(loaded) = map_get(storage, &SESSIONS, key, "session key") {
+        unindex_expiration(storage, key, &loaded.expiration);
+    }
+    map_save(storage, &SESSIONS, key, session, "session key")?;
+    index_expiration(storage, key, &session.expiration)
+}
+
+
+
+/// Remove the session together with all the sub-sessions created from it
+pub fn remove_session(
+    storage: &mut dyn Storage,
+    key: &str,
+) {
+    if let Some(parent) = SESSION_PARENTS.may_load(storage, key.to_string()).ok().flatten() {
+        SESSION_CHILDREN.remove(storage, (parent, key.to_string()));
+    }
+    let mut keys = vec![key.to_string()];
+
+    while let Some(key) = keys.pop() {
+        let children = SESSION_CHILDREN
+            .prefix(key.clone())
+            .keys(storage, None, None, Order::Ascending)
+            .filter_map(|child| child.ok())
+            .collect::<Vec<String>>();
+
+        children.iter().for_each(|child| SESSION_CHILDREN.remove(storage, (key.clone(), child.clone())));
+        keys.extend(children);
+
+        if let Ok(loaded) = map_get(storage, &SESSIONS, &key, "session key") {
+            unindex_expiration(storage, &key, &loaded.expiration);
+            if let Some(label) = SESSION_LABELS.may_load(storage, key.clone()).ok().flatten() {
+                LABELED_SESSIONS.remove(storage, (loaded.granter, loaded.grantee.0, label));
+            }
+        }
+        map_remove(storage, &SESSIONS, &key);
+        map_remove(storage, &SESSION_PARENTS, &key);
+        map_remove(storage, &SESSION_LABELS, &key);
+        remove_policy(storage, &key);
+    }
+}
+
+
+
+/// Key of the sub-session that the grantee already has under the given parent
+fn child_of_grantee(
+    storage: &dyn Storage,
+    parent: &str,
+    grantee: &str,
+) -> Option<String> {
+    SESSION_CHILDREN
+        .prefix(parent.to_string())
+        .keys(storage, None, None, Order::Ascending)
+        .filter_map(|child| child.ok())
+        .find(|child| SESSIONS
+            .may_load(storage, child.clone())
+            .ok()
+            .flatten()
+            .is_some_and(|s| s.grantee.0 == *grantee)
+        )
+}
+
+
+
+/// Revoke the sub-sessions that no longer fit into their updated parents together with their own children
+fn narrow_sub_sessions(
+    storage: &mut dyn Storage,
+    key: &str,
+) -> Result<Vec<String>, StorageError> {
+    let mut revoked = vec![];
+    let mut keys = vec![key.to_string()];
+
+    while let Some(key) = keys.pop() {
+        let parent = map_get(storage, &SESSIONS, &key, "session key")?;
+        let children = SESSION_CHILDREN
+            .prefix(key.clone())
+            .keys(storage, None, None, Order::Ascending)
+            .filter_map(|child| child.ok())
+            .collect::<Vec<String>>();
+
+        for child in children {
+            let session = map_get(storage, &SESSIONS, &child, "session key")?;
+            if check_parent_bounds(&session, &parent).is_ok() {
+                keys.push(child);
+            } else {
+                remove_session(storage, &child);
+                revoked.push(child);
+            }
+        }
+    }
+    Ok(revoked)
+}
+
+
+
+/// Keys of the parent sessions starting from the closest one if all of them are still valid
+fn valid_ancestors(
+    storage: &dyn Storage,
+    env: &Env,
+    key: &str,
+) -> Option<Vec<String>> {
+    let mut ancestors = vec![];
+    let mut current = key.to_string();
+
+    while let Some(parent) = SESSION_PARENTS.may_load(storage, current).ok().flatten() {
+        let loaded = map_get(storage, &SESSIONS, &parent, "session key").ok()?;
+        if loaded.expiration.is_expired(&env.block) {
+            return None;
+        }
+        ancestors.push(parent.clone());
+        current = parent;
+    }
+    Some(ancestors)
+}
+
+
+
+fn index_expiration(
+    storage: &mut dyn Storage,
+    key: &str,
+    expiration: &Expiration,
+) -> Result<(), StorageError> {
+    match expiration {
+        Expiration::AtHeight(height) => SESSION_EXPIRY_HEIGHTS.save(storage, (*height, key.to_string()), &Empty {}),
+        Expiration::AtTime(time) => SESSION_EXPIRY_TIMES.save(storage, (time.nanos(), key.to_string()), &Empty {}),
+        Expiration::Never {} => Ok(()),
+    }
+    .map_err(|e| StorageError::Write("session expiration".to_string(), e.to_string()))
+}
+
+
+
+fn unindex_expiration(
+    storage: &mut dyn Storage,
+    key: &str,
+    expiration: &Expiration,
+) {
+    match expiration {
+        Expiration::AtHeight(height) => SESSION_EXPIRY_HEIGHTS.remove(storage, (*height, key.to_string())),
+        Expiration::AtTime(time) => SESSION_EXPIRY_TIMES.remove(storage, (time.nanos(), key.to_string())),
+        Expiration::Never {} => {},
+    }
+}
+
+
+
+/// Whether the sender is one of the configured admins of the sessions
+fn is_session_admin(
+    storage: &dyn Storage,
+    env: &Env,
+    admins: &SessionAdmins,
+    sender: &str,
+) -> bool {
+    sender == env.contract.address.as_str() ||
+    admins.addresses.iter().any(|a| a == sender) ||
+    (
+        CREDENTIAL_INFOS.has(storage, sender.to_string()) && 
+        (admins.natives || admins.credentials.iter().any(|c| c == sender))
+    )
+}
+
+
+
+/// Granters whose sessions can be managed by the caller. The admins act on behalf of the account 
+/// so they can manage the sessions granted by the account itself and by each other
+fn managed_granters(
+    env: &Env,
+    granter: &str,
+    admins: Option<&SessionAdmins>,
+) -> Vec<String> {
+    let mut granters = vec![granter.to_string()];
+    if let Some(admins) = admins {
+        granters.push(env.contract.address.to_string());
+        granters.extend(admins.addresses.iter().cloned());
+    }
+    granters
+}
+
+
+
+/// Keys of the sessions matching the filter that can be revoked by the granters
+fn revocable_sessions(
+    storage: &dyn Storage,
+    env: &Env,
+    granters: &[String],
+    msg: RevokeSessionsMsg,
+) -> Result<Vec<String>, WasmAuthError> {
+    // anyone can revoke the expired sessions
+    let can_revoke = |session: &Session| {
+        granters.contains(&session.granter) || session.expiration.is_expired(&env.block)
+    };
+    match msg {
+        RevokeSessionsMsg::Keys(mut keys) => {
+            keys.sort();
+            keys.dedup();
+            keys.into_iter()
+                .map(|key| {
+                    let loaded = map_get(storage, &SESSIONS, &key, "session key")
+                        .map_err(|_| SessionError::NotFound)?;
+                    ensure!(can_revoke(&loaded), SessionError::NotOwner);
+                    Ok(key)
+                })
+                .collect()
+        },
+        filter => {
+            let keys = get_session_records(storage)?
+                .into_iter()
+                .filter(|(_, session)| can_revoke(session) && match &filter {
+                    RevokeSessionsMsg::Grantee(id) => session.grantee.0 == *id,
+                    RevokeSessionsMsg::Granter(address) => session.granter == *address,
+                    _ => true,
+                })
+                .map(|(key, _)| key)
+                .collect();
+            Ok(keys)
+        }
+    }
+}
+
+
+
+/// Remove up to `limit` expired sessions using the expiration indexes and return their keys
+pub fn prune_expired_sessions(
+    storage: &mut dyn Storage,
+    env: &Env,
+    limit: u32,
+) -> Result<Vec<String>, StorageError> {
+    let expired = expired_session_keys(storage, &env.block, limit as usize)?;
+    expired
+        .iter()
+        .for_each(|key| remove_session(storage, key));
+    Ok(expired)
+}
+
+
+
+pub fn session_config(
+    storage: &dyn Storage
+) -> SessionConfig {
+    SESSION_CONFIG.load(storage).unwrap_or_default()
+}
+
+
+
+pub fn save_session_config(
+    storage: &mut dyn Storage,
+    config: &SessionConfig,
+) -> Result<(), StorageError> {
+    SESSION_CONFIG.save(storage, config)
+        .map_err(|e| StorageError::Write("session config".to_string(), e.to_string()))
+}
+
+
+
+fn created_event(
+    key: &str,
+    session: &Session,
+    nonce: u64,
+    parent_key: Option<&String>,
+) -> Event {
+    SessionEvent::Created(SessionCreatedEvent {
+        session_key: key.to_string(),
+        granter: session.granter.clone(),
+        grantee: session.grantee.0.clone(),
+        nonce,
+        parent_key: parent_key.cloned(),
+    }).into()
+}
+
+
+fn used_event(
+    key: &str,
+    nonce: u64,
+) -> Event {
+    SessionEvent::Used(SessionUsedEvent { session_key: key.to_string(), nonce }).into()
+}
+
+
+fn key_event(
+    key: &str,
+    revoked: bool,
+) -> Event {
+    let session_key = key.to_string();
+    match revoked {
+        true => SessionEvent::Revoked(SessionRevokedEvent { session_key }),
+        false => SessionEvent::Expired(SessionExpiredEvent { session_key }),
+    }.into()
+}
+
+
+
 pub fn handle_session_action<M, F, E>(
     mut deps: DepsMut,
     env: &Env,
@@ -110,110 +582,251 @@ pub fn handle_session_action<M, F, E>(
     execute: F
 ) -> Result<Response, E> 
     where 
-        M : serde::de::DeserializeOwned + DerivableMsg + core::fmt::Display , 
+        M : serde::de::DeserializeOwned + Serialize + DerivableMsg + core::fmt::Display , 
         F: Fn(&mut DepsMut, &Env, &MessageInfo, MsgArg<M>) -> Result<Response, E>,
-        E: From<AuthError> + From<SessionError> + From<StorageError>,
+        E: From<WasmAuthError> + From<SessionError> + From<StorageError>,
 {
 
     //let addr = admin.clone().unwrap_or(info.sender.to_string());
     use SessionActionMsg::*;
+    let config = session_config(deps.storage);
 
-    Ok(match action.msg {
+    let res = match action.msg {
 
         WithSessionKey(with_msg) => {
             let key = &with_msg.session_key;
             let mut session = map_get(deps.storage, &SESSIONS, key, "session key")?;
+            let ancestors = valid_ancestors(deps.storage, env, key);
 
-            if session.expiration.is_expired(&env.block) {
+            if session.expiration.is_expired(&env.block) || ancestors.is_none() {
 
-                map_remove(deps.storage, &SESSIONS, key);
+                match config.on_expired.unwrap_or_default() {
+                    ExpiredKeyPolicy::Fail => return Err(SessionError::Expired.into()),
+                    ExpiredKeyPolicy::Revoke => remove_session(deps.storage, key),
+                }
                 Response::new()
                     .add_attribute("action", "with_session_key")
                     .add_attribute("session_key", key.as_str())
                     .add_attribute("status", "revoked")
                     .add_attribute("reason", "expired")
+                    .add_event(key_event(key, false))
 
             } else {
+                // sub-sessions are also bound by the policies of their parents
+                let keys = std::iter::once(key.clone())
+                    .chain(ancestors.unwrap_or_default())
+                    .collect::<Vec<String>>();
+
                 match action.signed {
                     Some(signed) => {
                         verify_session_signed(&mut deps, env, key, &mut session, with_msg.msgs.clone(), signed)?;
                     },
                     None => {
-                        verify_session_native(deps.as_ref(),  info.sender.as_str(), &session, &with_msg.msgs)?;
+                        verify_session_native(deps.as_ref(), info.sender.as_str(), key, &session, &with_msg.msgs)?;
                     }
                 };
+                for key in keys.iter() {
+                    check_session_policy(deps.storage, env, info, key, &with_msg.msgs)?;
+                }
                 execute(&mut deps, env, info, with_msg.msgs)?
                     .add_attribute("action", "with_session_key")
                     .add_attribute("session_key", key.as_str())
                     .add_attribute("status", "success")
                     .add_attribute("nonce", session.nonce.to_string().as_str())
+                    .add_event(used_event(key, session.nonce))
             }
         },
 
-        admin_action => {
-            let granter = admin.unwrap_or(env.contract.address.to_string());
-            #[cfg(feature = "signed")]
+        CreateSubSession(create) => {
+            let parent_key = &create.parent_key;
+            let mut parent = map_get(deps.storage, &SESSIONS, parent_key, "session key")
+                .map_err(|_| SubSessionError::InvalidParent)
+                .map_err(WasmAuthError::from)?;
+
+            ensure!(
+                !parent.expiration.is_expired(&env.block) &&
+                valid_ancestors(deps.storage, env, parent_key).is_some(), 
+                WasmAuthError::from(SubSessionError::InvalidParent)
+            );
+
             match action.signed {
                 Some(signed) => {
-                    verify_signed_actions(&mut deps, env, vec![admin_action.clone()], signed)?;
+                    let messages = vec![to_json_string(&create).map_err(WasmAuthError::from)?];
+                    verify_grantee_signed(&mut deps, env, parent_key, &mut parent, messages, signed)?;
                 },
                 None => {
-                    verify_native(deps.storage, granter.clone())?;
+                    verify_grantee_native(deps.as_ref(), info.sender.as_str(), &parent)?;
                 }
+            };
+
+            let session = create.to_session(env, &parent)?;
+            let key = format!("{}/{}", parent_key, session.key());
+            // a grantee can only have one live sub-session of the same parent
+            if let Some(existing) = child_of_grantee(deps.storage, parent_key, &session.grantee.0) {
+                let loaded = map_get(deps.storage, &SESSIONS, &existing, "session key")?;
+                ensure!(
+                    loaded.expiration.is_expired(&env.block), 
+                    WasmAuthError::from(SubSessionError::AlreadyExists(existing))
+                );
+                remove_session(deps.storage, &existing);
+            }
+            let nonce = update_session(deps.storage, &key, &session)?;
+            let event = created_event(&key, &session, nonce, Some(parent_key));
+            save_policy(deps.storage, &key, create.policy)?;
+            map_save(deps.storage, &SESSION_PARENTS, &key, parent_key, "session parent")?;
+            SESSION_CHILDREN
+                .save(deps.storage, (parent_key.clone(), key.clone()), &Empty {})
+                .map_err(|e| StorageError::Write("session child".to_string(), e.to_string()))?;
+
+            Response::new()
+                .add_attribute("action", "create_sub_session")
+                .add_attribute("session_key", key.as_str())
+                .add_attribute("parent_key", parent_key.as_str())
+                .add_attribute("nonce", nonce.to_string().as_str())
+                .add_event(event)
+        },
+
+        admin_action => {
+            let granter = admin.unwrap_or(env.contract.address.to_string());
+            // signed actions are authorized with the credentials of the account itself
+            #[cfg(feature = "signed")]
+            let signed = action.signed.is_some();
+            #[cfg(not(feature = "signed"))]
+            let signed = false;
+            // native calls come from the admins or without them from the native credentials and the contract itself
+            let authorized = match config.admins.as_ref() {
+                Some(admins) => is_session_admin(deps.storage, env, admins, info.sender.as_str()),
+                None => info.sender == env.contract.address 
+                    || verify_native(deps.storage, info.sender.to_string()).is_ok(),
+            };
+            ensure!(signed || authorized, SessionError::NotOwner);
+            let granters = managed_granters(env, &granter, config.admins.as_ref());
+            #[cfg(feature = "signed")]
+            if let Some(signed) = action.signed {
+                verify_signed_actions(&mut deps, env, vec![admin_action.clone()], signed)?;
             }
             match admin_action {
                 CreateSession(mut create) => {
                     create.session_info.granter = Some(granter);
-                    let session = create.to_session(&env)?;
-                    let key = session.key();
-                    let nonce = update_session(deps.storage,  &key, &session)?;
+                    let session = create.to_session(env)?;
+                    let key = creation_key(deps.storage, &session, create.label.as_ref())?;
+                    let mode = create.mode.unwrap_or_default();
+                    let nonce = create_session(deps.storage, env, &key, session.clone(), &mode, create.policy)?;
+                    save_label(deps.storage, &key, &session, create.label.as_ref())?;
+                    let event = created_event(&key, &session, nonce, None);
                     Response::new()
                         .add_attribute("action", "create_session")
                         .add_attribute("session_key", key.as_str())
+                        .add_attribute("mode", mode.to_string())
                         .add_attribute("nonce", nonce.to_string().as_str())
+                        .add_event(event)
                 }
                 CreateSessionFromMsg(mut create) => {
                     create.session_info.granter = Some(granter);
-                    let session = create.to_session(&env)?;
-                    let key = session.key();
-                    let nonce = update_session(deps.storage,  &key, &session)?;
-                    let msg = create.msgs;
+                    let session = create.to_session(env)?;
+                    let key = creation_key(deps.storage, &session, create.label.as_ref())?;
+                    let mode = create.mode.unwrap_or_default();
+                    let nonce = create_session(deps.storage, env, &key, session.clone(), &mode, create.policy)?;
+                    save_label(deps.storage, &key, &session, create.label.as_ref())?;
+                    let event = created_event(&key, &session, nonce, None);
+                    let msgs = create.msgs
+                        .into_iter()
+                        .map(|m| m.message)
+                        .collect::<Vec<M>>();
                     #[cfg(feature = "multi")]
-                    let msg = vec![msg];
-                    execute(&mut deps, env, info, msg)?
-                            .add_attribute("action", "create_session_from_msg")
+                    let res = execute(&mut deps, env, info, msgs)?;
+                    #[cfg(not(feature = "multi"))]
+                    let res = msgs
+                        .into_iter()
+                        .try_fold(Response::new(), |res, msg| {
+                            let executed = execute(&mut deps, env, info, msg)?;
+                            let merged = res
+                                .add_submessages(executed.messages)
+                                .add_attributes(executed.attributes)
+                                .add_events(executed.events);
+                            Ok::<Response, E>(match executed.data {
+                                Some(data) => merged.set_data(data),
+                                None => merged,
+                            })
+                        })?;
+                    res.add_attribute("action", "create_session_from_msg")
                             .add_attribute("session_key", key.as_str())
+                            .add_attribute("mode", mode.to_string())
                             .add_attribute("nonce", nonce.to_string().as_str())
+                            .add_event(event)
+                }
+                UpdateSession(update) => {
+                    let key = &update.session_key;
+                    let loaded = map_get(deps.storage, &SESSIONS, key, "session key")
+                        .map_err(|_| SessionError::NotFound)?;
+                    ensure!(granters.contains(&loaded.granter), SessionError::NotOwner);
+                    let session = update.to_session(env, &loaded)?;
+                    if let Some(parent) = SESSION_PARENTS.may_load(deps.storage, key.clone()).map_err(WasmAuthError::from)? {
+                        let parent = map_get(deps.storage, &SESSIONS, &parent, "session key")?;
+                        check_parent_bounds(&session, &parent).map_err(WasmAuthError::from)?;
+                    }
+                    save_session(deps.storage, key, &session)?;
+                    let revoked = narrow_sub_sessions(deps.storage, key)?;
+                    Response::new()
+                        .add_attribute("action", "update_session")
+                        .add_attribute("session_key", key.as_str())
+                        .add_attribute("nonce", session.nonce.to_string().as_str())
+                        .add_attribute("revoked", revoked.len().to_string())
+                        .add_events(revoked.iter().map(|key| key_event(key, true)))
                 }
                 RevokeSession(msg) => {
                     let key = &msg.session_key;
                     if let Ok(loaded) = map_get(deps.storage, &SESSIONS, key, "session key") {
                         // anyone can revoke the expired session
                         if !loaded.expiration.is_expired(&env.block) {
-                            ensure!(loaded.granter == granter, SessionError::NotOwner);
+                            ensure!(granters.contains(&loaded.granter), SessionError::NotOwner);
                         }
-                        map_remove(deps.storage, &SESSIONS, key);
+                        remove_session(deps.storage, key);
                         Response::new()
                             .add_attribute("action", "revoke_session")
                             .add_attribute("session_key", key.as_str())
                             .add_attribute("status", "revoked")
+                            .add_event(key_event(key, true))
                     } else {
                         return Err(SessionError::NotFound.into())
                     }       
                 }
+                RevokeSessions(msg) => {
+                    let keys = revocable_sessions(deps.storage, env, &granters, msg)?;
+                    keys.iter().for_each(|key| remove_session(deps.storage, key));
+                    Response::new()
+                        .add_attribute("action", "revoke_sessions")
+                        .add_attribute("revoked", keys.len().to_string())
+                        .add_events(keys.iter().map(|key| key_event(key, true)))
+                }
                 _ => unreachable!(),
             }
 
         }
-    })
+    };
+
+    match config.auto_prune {
+        Some(limit) => {
+            let pruned = prune_expired_sessions(deps.storage, env, limit)?;
+            if pruned.is_empty() {
+                Ok(res)
+            } else {
+                Ok(res
+                    .add_attribute("pruned_sessions", pruned.len().to_string())
+                    .add_events(pruned.iter().map(|key| key_event(key, false)))
+                )
+            }
+        },
+        None => Ok(res)
+    }
 }
 
 
 
 pub fn handle_session_query<M>(
     _api : &dyn Api,
-    _storage: &dyn Storage,
-    _env: &Env,
+    storage: &dyn Storage,
+    env: &Env,
     query: SessionQueryMsg<M>,
 ) -> StdResult<Binary> 
     where M: QueryUsesActions
@@ -282,5 +895,27 @@ pub fn handle_session_query<M>(
 
             to_json_binary(&act.result)
         }
+
+        SessionQueryMsg::Session { 
+            session_key 
+        } => {
+            let session = map_get(storage, &SESSIONS, &session_key, "session key")
+                .map_err(|e| StdError::generic_err(e.to_string()))?;
+            let policy = SESSION_POLICIES.may_load(storage, session_key.clone())?;
+
+            let remaining_actions = match policy.as_ref().and_then(|p| p.rate_limit.as_ref()) {
+                Some(rate_limit) => Some(
+                    remaining_actions(storage, env, &session_key, rate_limit)
+                        .map_err(|e| StdError::generic_err(e.to_string()))?
+                ),
+                None => None,
+            };
+
+            to_json_binary(&SessionResponse {
+                session,
+                policy,
+                remaining_actions,
+            })
+        }
     }
 }
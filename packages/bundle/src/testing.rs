@@ -0,0 +1,86 @@
+use smart_account_auth::{Credential, CredentialData};
+use types::wasm::{Env, StdResult};
+#[cfg(feature = "eip712")]
+use {smart_account_auth::msgs::SignedDataMsg, types::wasm::to_json_binary, crate::TypedDataToSign};
+
+pub use client::{
+    credential_data, sign_credential, signed_data_msg, AccountContext, CosmosSigner, 
+    CredentialSigner, DataSigner, Ed25519Signer, EthSigner, PasskeySigner
+};
+
+
+/// Origin of the passkeys of `TestSigners`
+pub const TEST_PASSKEY_ORIGIN : &str = "http://localhost:5173";
+
+
+
+/// Deterministic key pairs of every supported kind derived from the same seed. 
+/// Sign any envelope on the fly for the unit tests of the contracts
+#[derive(Clone, Debug)]
+pub struct TestSigners {
+    pub eth         :   EthSigner,
+    pub cosmos      :   CosmosSigner,
+    pub passkey     :   PasskeySigner,
+    pub ed25519     :   Ed25519Signer,
+}
+
+
+
+impl TestSigners {
+
+    /// Cosmos addresses use the given bech32 prefix
+    pub fn new(seed: &[u8], hrp: &str) -> StdResult<Self> {
+        let seed_of = |kind: &str| [seed, kind.as_bytes()].concat();
+        Ok(Self {
+            eth: EthSigner::from_seed(&seed_of("eth"))?,
+            cosmos: CosmosSigner::from_seed(&seed_of("cosmos"), hrp)?,
+            passkey: PasskeySigner::from_seed(&seed_of("passkey"), TEST_PASSKEY_ORIGIN)?,
+            ed25519: Ed25519Signer::from_seed(&seed_of("ed25519")),
+        })
+    }
+
+    /// All the signers starting from the Ethereum one
+    pub fn all(&self) -> Vec<&dyn CredentialSigner> {
+        vec![&self.eth, &self.cosmos, &self.passkey, &self.ed25519]
+    }
+
+    /// Credentials of every signer for the envelope of the contract at the given account number
+    pub fn credentials(
+        &self, 
+        env: &Env, 
+        nonce: u64, 
+        messages: Vec<String>
+    ) -> StdResult<Vec<Credential>> {
+        let ctx = AccountContext::from_env(env, nonce);
+        self.all()
+            .into_iter()
+            .map(|signer| sign_credential(signer, &ctx, messages.clone()))
+            .collect()
+    }
+
+    /// Credential data of every signer for the envelope of the contract at the given account number
+    pub fn credential_data(
+        &self, 
+        env: &Env, 
+        nonce: u64, 
+        messages: Vec<String>,
+        with_native: Option<bool>,
+    ) -> StdResult<CredentialData> {
+        credential_data(&self.all(), &AccountContext::from_env(env, nonce), messages, with_native)
+    }
+}
+
+
+
+/// Signed data with the EIP-712 typed data envelope signed by the Ethereum wallet
+#[cfg(feature = "eip712")]
+pub fn sign_typed_data(
+    signer: &EthSigner,
+    typed: &TypedDataToSign,
+) -> StdResult<SignedDataMsg> {
+    Ok(SignedDataMsg {
+        data: to_json_binary(typed)?,
+        signature: signer.sign_hash(&typed.digest())?,
+        payload: None,
+    })
+}
@@ -1,12 +1,13 @@
 use types::{
-    errors::{AuthError, StorageError}, stores::{get_map_records, map_get, CREDENTIAL_INFOS as CREDS}, 
-    wasm::{Deps, Storage}
+    errors::{AuthError, StorageError}, stores::{get_map_records, CREDENTIAL_INFOS as CREDS}, 
+    wasm::{Binary, Storage}
 };
 
 use smart_account_auth::{
-    build_credential, msgs::SignedDataMsg, 
-    types::{errors::CredentialError, exts::PayloadExtension}, 
-    Credential, CredentialRecord
+    msgs::SignedDataMsg, 
+    types::{errors::CredentialError, exts::{InfoExtension, PayloadExtension}, ClientData, PasskeyPayload}, 
+    CosmosArbitrary, Credential, CredentialName, CredentialRecord, Ed25519, 
+    EthPersonalSign, PasskeyCredential
 };
 
 
@@ -29,20 +30,70 @@ pub fn get_credentials(
 
 
 
+#[cfg(any(feature = "signed", feature = "ibc"))]
 pub fn cred_from_signed(
-    deps: Deps,
+    deps: types::wasm::Deps,
     msg: SignedDataMsg,
 ) -> Result<Credential, AuthError> {
+    let (id, info, ext) = signer_info(deps.storage, &msg)?;
+    let cred = build_credential((id, info), msg, ext)?;
+    cred.validate()?;
+    verify_credential(deps, &cred)?;
+    Ok(cred)
+}
+
+
+
+/// Verifies the signature of a credential. Passkeys are checked through the `Api` here since the
+/// upstream check is skipped whenever the `cosmwasm_v1` feature is enabled anywhere in the build
+#[cfg(any(feature = "session", feature = "signed", feature = "ibc"))]
+pub(crate) fn verify_credential(
+    deps: types::wasm::Deps,
+    cred: &Credential,
+) -> Result<smart_account_auth::CredentialInfo, AuthError> {
+    #[cfg(feature = "cosmwasm")]
+    if let Credential::Passkey(passkey) = cred {
+        use sha2::{Digest, Sha256};
+        let client_data = types::wasm::to_json_vec(&passkey.client_data)?;
+        let hash = Sha256::digest([
+            passkey.authenticator_data.as_slice(), 
+            Sha256::digest(client_data).as_slice()
+        ].concat());
+        let pubkey = passkey.pubkey.as_ref()
+            .ok_or_else(|| AuthError::MissingData("Public key".to_string()))?;
+        let valid = deps.api.secp256r1_verify(&hash, &passkey.signature, pubkey)?;
+        types::wasm::ensure!(valid, AuthError::Signature(CredentialName::Passkey, passkey.id.clone()));
+        return Ok(smart_account_auth::CredentialInfo { 
+            name: CredentialName::Passkey, extension: None, address: None, hrp: None 
+        });
+    }
+    // CosmWasm 1.x has no secp256r1 in the api and the upstream check accepts any signature
+    #[cfg(not(feature = "cosmwasm"))]
+    types::wasm::ensure!(
+        !matches!(cred, Credential::Passkey(_)), 
+        AuthError::generic("Passkeys can only be verified with the `cosmwasm` feature")
+    );
+    cred.verify(deps)
+}
+
+
+
+/// ID and stored info of the credential that has signed the message
+#[cfg(any(feature = "signed", feature = "ibc"))]
+pub(crate) fn signer_info(
+    storage: &dyn Storage,
+    msg: &SignedDataMsg,
+) -> Result<(String, smart_account_auth::CredentialInfo, Option<PayloadExtension>), AuthError> {
+    use types::stores::map_get;
     let (id, hrp, ext) = parse_cred_args(
-        types::stores::PRIMARY_ID.load(deps.storage)
+        types::stores::PRIMARY_ID.load(storage)
             .map_err(|_| CredentialError::NoCredentials)?.as_str(),
-        &msg
+        msg
     );
-    let mut info = map_get(deps.storage, &CREDS, &id, "credential")?;
+    // the IDs are stored in lowercase while the payload may pass the original key
+    let mut info = map_get(storage, &CREDS, id.to_lowercase(), "credential")?;
     info.hrp = hrp.or(info.hrp);
-    let cred = build_credential((id, info), msg, ext)?;
-    cred.verify(deps)?;
-    Ok(cred)
+    Ok((id, info, ext))
 }
 
 
@@ -81,25 +132,97 @@ pub fn credential_count(storage: &dyn Storage) -> usize {
 
 
 
+/// Build the credential of the session grantee without verifying it
 #[cfg(feature = "session")]
-pub fn session_cred_from_signed(
-    deps: Deps,
-    key: &str,
+pub(crate) fn session_cred(
+    session: &smart_account_auth::Session,
     msg: SignedDataMsg,
 ) -> Result<Credential, AuthError> {
-    use smart_account_auth::build_credential;
+    let (id, info) = &session.grantee;
+    let (id, hrp, ext) = parse_cred_args(id, &msg);
+    let mut info = info.clone();
+    info.hrp = hrp.or(info.hrp);
+    Ok(build_credential((id, info), msg, ext)?)
+}
 
-    let (id, hrp, ext) = parse_cred_args(key, &msg);
-    let session = map_get(
-        deps.storage, &types::stores::SESSIONS, &id, "session key")
-        .map_err(|e| AuthError::generic(e.to_string())
-    )?;
 
-    let mut info = session.grantee.1.clone();
-    info.hrp = hrp.or(info.hrp);
-    let cred = build_credential((id, info), msg, ext)?;
-    cred.verify(deps)?;
-    Ok(cred)
+
+/// Credential that has signed the message rebuilt from its stored record. The keys of the credentials
+/// that are stored in lowercase must come from the payload for rebuilding them. Passkeys take the
+/// authenticator data of the assertion from a `Custom` payload extension since their sign counters change
+/// and fall back to the stored one
+fn build_credential(
+    (id, info): CredentialRecord,
+    msg: SignedDataMsg,
+    payload: Option<PayloadExtension>,
+) -> Result<Credential, CredentialError> {
+    let message = msg.data;
+    let signature = msg.signature;
+    let pubkey = |name: CredentialName| Binary::from_base64(&id)
+        .map_err(|e| CredentialError::InvalidProperty(name, "pubkey".into(), e.to_string()));
+
+    let credential = match info.name {
+        CredentialName::EthPersonalSign => Credential::EthPersonalSign(EthPersonalSign {
+            message,
+            signature,
+            signer: id,
+        }),
+
+        CredentialName::CosmosArbitrary => Credential::CosmosArbitrary(CosmosArbitrary {
+            pubkey: pubkey(CredentialName::CosmosArbitrary)?,
+            message,
+            signature,
+            address: info.address
+                .ok_or_else(|| CredentialError::NoInfoProperty(
+                    CredentialName::CosmosArbitrary, "address".into()))?
+                .to_string(),
+        }),
+
+        CredentialName::Ed25519 => Credential::Ed25519(Ed25519 {
+            pubkey: pubkey(CredentialName::Ed25519)?,
+            message,
+            signature,
+        }),
+
+        CredentialName::Passkey => {
+            let Some(InfoExtension::Passkey(info_ext)) = info.extension else {
+                return Err(CredentialError::NoInfoExt(CredentialName::Passkey))
+            };
+            let (origin, other_keys, authenticator_data) = match payload {
+                Some(PayloadExtension::Passkey(PasskeyPayload { origin, other_keys })) => (origin, other_keys, None),
+                Some(PayloadExtension::Custom(data)) => (None, None, Some(data)),
+                _ => (None, None, None),
+            };
+            let authenticator_data = match authenticator_data {
+                Some(data) => {
+                    // the relying party of the assertion must be the one of the stored passkey
+                    let rp_id_hash = |data: &Binary| data.get(..32).map(|hash| hash.to_vec());
+                    if data.len() < 37 || rp_id_hash(&data) != rp_id_hash(&info_ext.authenticator_data) {
+                        return Err(CredentialError::InvalidProperty(
+                            CredentialName::Passkey, "authenticator_data".into(), "Relying party mismatch".into()
+                        ));
+                    }
+                    data
+                },
+                None => info_ext.authenticator_data,
+            };
+            let challenge = message.to_base64().replace('+', "-").replace('/', "_").replace('=', "");
+            Credential::Passkey(PasskeyCredential {
+                id,
+                signature,
+                client_data: ClientData::new(
+                    challenge, origin.unwrap_or(info_ext.origin), info_ext.cross_origin, other_keys
+                ),
+                pubkey: Some(info_ext.pubkey),
+                authenticator_data,
+                user_handle: info_ext.user_handle,
+            })
+        },
+        // native callers don't sign anything and typed data is rebuilt 
+        // from the envelope by the `eip712` feature
+        name => return Err(CredentialError::IncorrectData(name)),
+    };
+    Ok(credential)
 }
 
 
@@ -0,0 +1,53 @@
+use smart_account_auth::msgs::MsgDataToSign;
+use types::wasm::{to_json_binary, Binary, Env, StdResult, Uint64};
+
+
+
+/// What the envelope is bound to: the chain and the address of the account and its current account number
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountContext {
+    pub chain_id            :   String,
+    pub contract_address    :   String,
+    pub account_number      :   u64,
+}
+
+
+
+impl AccountContext {
+
+    pub fn new(
+        chain_id: impl Into<String>, 
+        contract_address: impl Into<String>, 
+        account_number: u64
+    ) -> Self {
+        Self {
+            chain_id: chain_id.into(),
+            contract_address: contract_address.into(),
+            account_number,
+        }
+    }
+
+    /// Context of the contract executing in the given environment
+    pub fn from_env(env: &Env, account_number: u64) -> Self {
+        Self::new(env.block.chain_id.clone(), env.contract.address.to_string(), account_number)
+    }
+
+    /// Same context after the account number has been used
+    pub fn next(&self) -> Self {
+        Self { account_number: self.account_number + 1, ..self.clone() }
+    }
+
+    pub fn envelope(&self, messages: Vec<String>) -> MsgDataToSign {
+        MsgDataToSign {
+            chain_id: self.chain_id.clone(),
+            contract_address: self.contract_address.clone(),
+            messages,
+            nonce: Uint64::new(self.account_number),
+        }
+    }
+
+    /// JSON encoded envelope as it gets signed
+    pub fn message(&self, messages: Vec<String>) -> StdResult<Binary> {
+        to_json_binary(&self.envelope(messages))
+    }
+}
@@ -0,0 +1,51 @@
+mod context;
+mod signers;
+
+pub use {k256, p256, ed25519_dalek};
+pub use context::AccountContext;
+pub use signers::{
+    CredentialSigner, DataSigner, CosmosSigner, Ed25519Signer, EthSigner, PasskeySigner
+};
+
+use smart_account_auth::{msgs::SignedDataMsg, Credential, CredentialData};
+use types::wasm::StdResult;
+
+
+
+/// Credential signing the envelope of the messages for the given account
+pub fn sign_credential(
+    signer: &dyn CredentialSigner,
+    ctx: &AccountContext,
+    messages: Vec<String>,
+) -> StdResult<Credential> {
+    signer.credential(ctx.message(messages)?)
+}
+
+
+
+/// Credential data where every signer has signed the same envelope. Used for saving 
+/// the credentials of a new account or for adding them to an existing one
+pub fn credential_data(
+    signers: &[&dyn CredentialSigner],
+    ctx: &AccountContext,
+    messages: Vec<String>,
+    with_native: Option<bool>,
+) -> StdResult<CredentialData> {
+    let message = ctx.message(messages)?;
+    let credentials = signers
+        .iter()
+        .map(|signer| signer.credential(message.clone()))
+        .collect::<StdResult<Vec<Credential>>>()?;
+    Ok(CredentialData::new(credentials, with_native))
+}
+
+
+
+/// Envelope of the messages signed by the primary credential of the account
+pub fn signed_data_msg(
+    signer: &dyn DataSigner,
+    ctx: &AccountContext,
+    messages: Vec<String>,
+) -> StdResult<SignedDataMsg> {
+    signer.signed_data(ctx.message(messages)?)
+}
@@ -0,0 +1,360 @@
+use bech32::{Bech32, Hrp};
+use k256::ecdsa::{signature::Signer, SigningKey as Secp256k1Key};
+use p256::ecdsa::{Signature as Secp256r1Signature, SigningKey as Secp256r1Key};
+use ed25519_dalek::SigningKey as Ed25519Key;
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use tiny_keccak::{Hasher, Keccak};
+
+use smart_account_auth::{
+    msgs::{AuthPayload, SignedDataMsg}, types::{exts::PayloadExtension, ClientData, PasskeyInfo}, utils::passkey::base64_to_url,
+    CosmosArbitrary, Credential, Ed25519, EthPersonalSign, PasskeyCredential
+};
+use types::{adr036::wrap_adr036, wasm::{to_json_vec, Binary, StdError, StdResult}};
+
+
+
+/// Anything that can produce a credential verifying a message
+pub trait CredentialSigner {
+    /// Credential with the signature of the given message
+    fn credential(&self, message: Binary) -> StdResult<Credential>;
+
+    /// ID of the credential as it is stored by the account
+    fn credential_id(&self) -> StdResult<String> {
+        Ok(self.credential(Binary::default())?.id().to_lowercase())
+    }
+}
+
+
+/// Signers whose signature alone is enough for the account to rebuild the credential 
+/// from the stored info. Passkeys rebuild it only when their `PasskeyInfo` has been stored
+pub trait DataSigner : CredentialSigner {
+    fn sign(&self, message: &[u8]) -> StdResult<Binary>;
+
+    /// Payload telling the account which credential to rebuild
+    fn payload(&self) -> Option<AuthPayload> {
+        None
+    }
+
+    /// Signed data verified by the credential of the signer
+    fn signed_data(&self, data: Binary) -> StdResult<SignedDataMsg> {
+        Ok(SignedDataMsg {
+            signature: self.sign(&data)?,
+            data,
+            payload: self.payload(),
+        })
+    }
+}
+
+
+
+/// Cosmos wallet signing arbitrary data with `signArbitrary` (ADR-036)
+#[derive(Clone, Debug)]
+pub struct CosmosSigner {
+    key         :   Secp256k1Key,
+    hrp         :   Hrp,
+}
+
+
+/// Ethereum wallet signing with `personal_sign`
+#[derive(Clone, Debug)]
+pub struct EthSigner {
+    key         :   Secp256k1Key,
+}
+
+
+/// WebAuthn authenticator signing the envelope as the challenge
+#[derive(Clone, Debug)]
+pub struct PasskeySigner {
+    key         :   Secp256r1Key,
+    id          :   String,
+    origin      :   String,
+    counter     :   u32,
+}
+
+
+/// Plain Ed25519 key signing the SHA-256 hash of the envelope
+#[derive(Clone, Debug)]
+pub struct Ed25519Signer {
+    key         :   Ed25519Key,
+}
+
+
+
+impl CosmosSigner {
+
+    /// Fails on an invalid bech32 prefix
+    pub fn new(key: Secp256k1Key, hrp: &str) -> StdResult<Self> {
+        let hrp = Hrp::parse(hrp)
+            .map_err(|e| StdError::generic_err(format!("Invalid bech32 prefix: {}", e)))?;
+        Ok(Self { key, hrp })
+    }
+
+    /// Key derived from `sha256(seed)`
+    pub fn from_seed(seed: &[u8], hrp: &str) -> StdResult<Self> {
+        Self::new(secp256k1_key(seed)?, hrp)
+    }
+
+    pub fn key(&self) -> &Secp256k1Key {
+        &self.key
+    }
+
+    /// Compressed public key
+    pub fn pubkey(&self) -> Binary {
+        Binary::from(self.key.verifying_key().to_encoded_point(true).as_bytes())
+    }
+
+    pub fn address(&self) -> String {
+        let hash = Ripemd160::digest(sha256(&self.pubkey()));
+        // a valid prefix with a 20 bytes hash is always within the length limit of bech32
+        bech32::encode::<Bech32>(self.hrp, &hash).unwrap_or_default()
+    }
+}
+
+
+impl CredentialSigner for CosmosSigner {
+    fn credential(&self, message: Binary) -> StdResult<Credential> {
+        Ok(Credential::CosmosArbitrary(CosmosArbitrary {
+            pubkey: self.pubkey(),
+            signature: self.sign(&message)?,
+            message,
+            address: self.address(),
+        }))
+    }
+}
+
+
+impl DataSigner for CosmosSigner {
+    fn sign(&self, message: &[u8]) -> StdResult<Binary> {
+        let doc = wrap_adr036(&Binary::from(message), &self.address())?;
+        let signature : k256::ecdsa::Signature = self.key.sign(&doc);
+        Ok(Binary::from(signature.to_bytes().to_vec()))
+    }
+
+    fn payload(&self) -> Option<AuthPayload> {
+        key_payload(self.pubkey())
+    }
+}
+
+
+
+impl EthSigner {
+
+    pub fn new(key: Secp256k1Key) -> Self {
+        Self { key }
+    }
+
+    /// Key derived from `sha256(seed)`
+    pub fn from_seed(seed: &[u8]) -> StdResult<Self> {
+        Ok(Self::new(secp256k1_key(seed)?))
+    }
+
+    pub fn key(&self) -> &Secp256k1Key {
+        &self.key
+    }
+
+    /// Checksum-less hex address with the `0x` prefix
+    pub fn address(&self) -> String {
+        let pubkey = self.key.verifying_key().to_encoded_point(false);
+        format!("0x{}", hex(&keccak256(&pubkey.as_bytes()[1..])[12..]))
+    }
+
+    /// Recoverable signature of an already hashed message e.g. of the digest of EIP-712 typed data
+    pub fn sign_hash(&self, hash: &[u8]) -> StdResult<Binary> {
+        let (signature, recovery) = self.key
+            .sign_prehash_recoverable(hash)
+            .map_err(|e| StdError::generic_err(format!("Ethereum signing failed: {}", e)))?;
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(recovery.to_byte() + 27);
+        Ok(Binary::from(bytes))
+    }
+}
+
+
+impl CredentialSigner for EthSigner {
+    fn credential(&self, message: Binary) -> StdResult<Credential> {
+        Ok(Credential::EthPersonalSign(EthPersonalSign {
+            signer: self.address(),
+            signature: self.sign(&message)?,
+            message,
+        }))
+    }
+}
+
+
+impl DataSigner for EthSigner {
+    fn sign(&self, message: &[u8]) -> StdResult<Binary> {
+        let prefixed = [
+            format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes(),
+            message
+        ].concat();
+        self.sign_hash(&keccak256(&prefixed))
+    }
+}
+
+
+
+impl PasskeySigner {
+
+    pub fn new(key: Secp256r1Key, id: impl Into<String>, origin: impl Into<String>) -> Self {
+        Self { key, id: id.into(), origin: origin.into(), counter: 0 }
+    }
+
+    /// Same signer whose authenticator reports the given sign counter
+    pub fn with_counter(mut self, counter: u32) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    /// Key derived from `sha256(seed)` with the credential ID derived from the key
+    pub fn from_seed(seed: &[u8], origin: impl Into<String>) -> StdResult<Self> {
+        let key = Secp256r1Key::from_slice(&sha256(seed))
+            .map_err(|e| StdError::generic_err(format!("Invalid secp256r1 key: {}", e)))?;
+        let id = base64_to_url(&Binary::from(sha256(&sha256(seed))).to_base64());
+        Ok(Self::new(key, id, origin))
+    }
+
+    /// Uncompressed public key
+    pub fn pubkey(&self) -> Binary {
+        Binary::from(self.key.verifying_key().to_encoded_point(false).as_bytes())
+    }
+
+    /// Info extension for rebuilding the credential from the signature alone
+    pub fn info(&self) -> PasskeyInfo {
+        PasskeyInfo {
+            authenticator_data: self.authenticator_data(),
+            origin: self.origin.clone(),
+            pubkey: self.pubkey(),
+            user_handle: None,
+            cross_origin: false,
+        }
+    }
+
+    /// Hash of the relying party ID taken from the origin with the user presence and verification flags
+    /// followed by the sign counter
+    pub fn authenticator_data(&self) -> Binary {
+        let rp_id = self.origin
+            .split("://")
+            .last()
+            .and_then(|host| host.split([':', '/']).next())
+            .unwrap_or_default();
+        Binary::from([&sha256(rp_id.as_bytes())[..], &[0x05], &self.counter.to_be_bytes()].concat())
+    }
+}
+
+
+impl CredentialSigner for PasskeySigner {
+    fn credential(&self, message: Binary) -> StdResult<Credential> {
+        let client_data = ClientData::new(
+            base64_to_url(&message.to_base64()), self.origin.as_str(), false, None
+        );
+        let client_json = to_json_vec(&client_data)?;
+        let authenticator_data = self.authenticator_data();
+
+        let signed = [authenticator_data.as_slice(), &sha256(&client_json)].concat();
+        let signature : Secp256r1Signature = self.key.sign(&signed);
+        let signature = signature.normalize_s().unwrap_or(signature);
+
+        Ok(Credential::Passkey(PasskeyCredential {
+            id: self.id.clone(),
+            authenticator_data,
+            signature: Binary::from(signature.to_bytes().to_vec()),
+            pubkey: Some(self.pubkey()),
+            client_data,
+            user_handle: None,
+        }))
+    }
+}
+
+
+
+impl DataSigner for PasskeySigner {
+    fn sign(&self, message: &[u8]) -> StdResult<Binary> {
+        match self.credential(Binary::from(message))? {
+            Credential::Passkey(passkey) => Ok(passkey.signature),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Authenticator data of the assertion since the sign counter differs from the stored one
+    fn payload(&self) -> Option<AuthPayload> {
+        Some(AuthPayload {
+            credential_id: None,
+            hrp: None,
+            extension: Some(PayloadExtension::Custom(self.authenticator_data())),
+        })
+    }
+}
+
+
+
+impl Ed25519Signer {
+
+    pub fn new(key: Ed25519Key) -> Self {
+        Self { key }
+    }
+
+    /// Key derived from `sha256(seed)`
+    pub fn from_seed(seed: &[u8]) -> Self {
+        Self::new(Ed25519Key::from_bytes(&sha256(seed)))
+    }
+
+    pub fn pubkey(&self) -> Binary {
+        Binary::from(self.key.verifying_key().to_bytes().to_vec())
+    }
+}
+
+
+impl CredentialSigner for Ed25519Signer {
+    fn credential(&self, message: Binary) -> StdResult<Credential> {
+        Ok(Credential::Ed25519(Ed25519 {
+            pubkey: self.pubkey(),
+            signature: self.sign(&message)?,
+            message,
+        }))
+    }
+}
+
+
+impl DataSigner for Ed25519Signer {
+    /// The verifying side checks the signature of `sha256(message)`
+    fn sign(&self, message: &[u8]) -> StdResult<Binary> {
+        Ok(Binary::from(self.key.sign(&sha256(message)).to_bytes().to_vec()))
+    }
+
+    fn payload(&self) -> Option<AuthPayload> {
+        key_payload(self.pubkey())
+    }
+}
+
+
+
+/// The accounts store the IDs in lowercase so the keys themselves are passed for rebuilding the credentials
+fn key_payload(pubkey: Binary) -> Option<AuthPayload> {
+    Some(AuthPayload { credential_id: Some(pubkey.to_base64()), hrp: None, extension: None })
+}
+
+
+fn secp256k1_key(seed: &[u8]) -> StdResult<Secp256k1Key> {
+    Secp256k1Key::from_slice(&sha256(seed))
+        .map_err(|e| StdError::generic_err(format!("Invalid secp256k1 key: {}", e)))
+}
+
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(data);
+    hasher.finalize(&mut hash);
+    hash
+}
+
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
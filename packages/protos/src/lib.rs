@@ -93,7 +93,7 @@ pub fn session_action(metadata: TokenStream, input: TokenStream) -> TokenStream
             .into();
         }
     };
-    let act_id = non_session_ty.get_ident().clone().unwrap();
+    let act_id = non_session_ty.get_ident().unwrap();
     // println!("act_id: {:?}", act_id);
     
     // Parse the enum itself
@@ -0,0 +1,74 @@
+use client::{
+    credential_data, sign_credential, signed_data_msg, AccountContext, 
+    CosmosSigner, CredentialSigner, Ed25519Signer, EthSigner, PasskeySigner
+};
+use saa_wasm::{
+    cred_from_signed, credential_count, has_credential, save_credentials, verify_cred_actions
+};
+use smart_account_auth::{CheckOption, CredentialsWrapper, ReplayParams};
+use types::stores::{ACCOUNT_NUMBER, PRIMARY_ID};
+
+use crate::utils::{get_mock_deps, get_mock_env, person_info, ALICE_ADDR, EVE_ADDR, MESSAGE_TEXT};
+
+
+
+#[test]
+fn client_signers_work() {
+    let mut mocks = get_mock_deps();
+    let env = get_mock_env();
+    let alice = person_info(ALICE_ADDR);
+    let messages = vec![MESSAGE_TEXT.to_string()];
+    let params = |nonce| ReplayParams::new(nonce, CheckOption::Messages(messages.clone()));
+
+    let eth = EthSigner::from_seed(b"eth").unwrap();
+    let cosmos = CosmosSigner::from_seed(b"cosmos", "stars").unwrap();
+    let passkey = PasskeySigner::from_seed(b"passkey", "http://localhost:5173").unwrap();
+    let ed25519 = Ed25519Signer::from_seed(b"ed25519");
+    let signers : [&dyn CredentialSigner; 4] = [&eth, &cosmos, &passkey, &ed25519];
+
+    assert!(cosmos.address().starts_with("stars1"));
+    assert_eq!(eth.address(), EthSigner::from_seed(b"eth").unwrap().address());
+    assert!(CosmosSigner::from_seed(b"cosmos", "not a prefix").is_err());
+
+    // signed for another account
+    let other = AccountContext::new(env.block.chain_id.clone(), EVE_ADDR, 0);
+    let data = credential_data(&signers, &other, messages.clone(), None).unwrap();
+    assert!(data.verify(mocks.as_ref(), &env, &alice, params(0)).is_err());
+
+    let ctx = AccountContext::from_env(&env, 0);
+    let data = credential_data(&signers, &ctx, messages.clone(), None).unwrap();
+    let verified = data.verify(mocks.as_ref(), &env, &alice, params(0)).unwrap();
+    save_credentials(mocks.as_mut().storage, &verified).unwrap();
+
+    let storage = mocks.as_ref().storage;
+    assert_eq!(credential_count(storage), 4);
+    assert_eq!(ACCOUNT_NUMBER.load(storage).unwrap(), 1);
+    assert_eq!(PRIMARY_ID.load(storage).unwrap(), eth.credential_id().unwrap());
+    signers
+        .iter()
+        .for_each(|signer| assert!(has_credential(storage, signer.credential_id().unwrap(), None)));
+
+    // every credential can sign the next actions
+    let mut ctx = ctx.next();
+    for signer in signers {
+        let cred = sign_credential(signer, &ctx, messages.clone()).unwrap();
+        verify_cred_actions(&mocks.api, &mut mocks.storage, &env, cred.clone(), Some(messages.clone())).unwrap();
+        // can't be replayed
+        assert!(verify_cred_actions(&mocks.api, &mut mocks.storage, &env, cred, Some(messages.clone())).is_err());
+        ctx = ctx.next();
+    }
+    assert_eq!(ACCOUNT_NUMBER.load(mocks.as_ref().storage).unwrap(), 5);
+
+    // signed data is verified by the primary credential
+    let signed = signed_data_msg(&eth, &ctx, messages.clone()).unwrap();
+    assert!(cred_from_signed(mocks.as_ref(), signed).is_ok());
+    // any other stored credential passing its key in the payload
+    let signed = signed_data_msg(&ed25519, &ctx, messages.clone()).unwrap();
+    assert!(cred_from_signed(mocks.as_ref(), signed).is_ok());
+    let signed = signed_data_msg(&cosmos, &ctx, messages.clone()).unwrap();
+    assert!(cred_from_signed(mocks.as_ref(), signed).is_ok());
+    // but not the keys that haven't been stored
+    let stranger = Ed25519Signer::from_seed(b"stranger");
+    let signed = signed_data_msg(&stranger, &ctx, messages).unwrap();
+    assert!(cred_from_signed(mocks.as_ref(), signed).is_err());
+}
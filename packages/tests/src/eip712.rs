@@ -0,0 +1,240 @@
+use saa_wasm::{
+    testing::{sign_typed_data, EthSigner},
+    handle_session_action, verify_cred_actions, verify_signed, verify_signed_actions,
+    BlockBound, CreateSession, SessionAction, SessionActionMsg, TypedBounds, TypedDataToSign, ValidityBounds, WasmAuthError, WithSessionMsg
+};
+use smart_account_auth::{
+    msgs::{Action, ActionDerivation, AllowedActions, SignedDataMsg}, 
+    Credential, CredentialInfo, CredentialName, EthPersonalSign
+};
+use types::{
+    errors::{EnvelopeError, ValidityError},
+    stores::{ACCOUNT_NUMBER, CREDENTIAL_INFOS, PRIMARY_ID},
+    wasm::{to_json_binary, to_json_string, DepsMut, Env, Response, Storage, Timestamp}
+};
+
+use crate::{
+    types::ActionMsg, 
+    utils::{get_account_deps, test_signers, get_mock_env, person_info, session_info, ALICE_ADDR, BOB_ADDR}
+};
+
+
+
+fn save_eth(storage: &mut dyn Storage, address: &str) {
+    let info = CredentialInfo {
+        name: CredentialName::EthPersonalSign,
+        hrp: None,
+        extension: None,
+        address: None,
+    };
+    CREDENTIAL_INFOS.save(storage, address.to_lowercase(), &info).unwrap();
+    PRIMARY_ID.save(storage, &address.to_lowercase()).unwrap();
+}
+
+
+fn typed_cred(signer: &EthSigner, address: &str, typed: &TypedDataToSign) -> Credential {
+    let signed = sign_typed_data(signer, typed).unwrap();
+    Credential::EthPersonalSign(EthPersonalSign {
+        message: signed.data,
+        signature: signed.signature,
+        signer: address.to_string(),
+    })
+}
+
+
+fn run_signed(
+    deps: DepsMut,
+    env: &Env,
+    key: &str,
+    signed: SignedDataMsg,
+) -> Result<Response, WasmAuthError> {
+    handle_session_action(
+        deps, env, &person_info(ALICE_ADDR), 
+        SessionAction { 
+            msg: SessionActionMsg::WithSessionKey(WithSessionMsg { 
+                session_key: key.to_string(), 
+                msgs: ActionMsg::Freeze {} 
+            }), 
+            signed: Some(signed) 
+        }, 
+        None, 
+        |_, _, _, _| Ok(Response::new())
+    )
+}
+
+
+
+#[test]
+fn typed_data_sessions_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let signer = test_signers().eth;
+    let address = signer.address();
+    let other = EthSigner::from_seed(b"other").unwrap();
+
+    let mut info = session_info();
+    info.grantee = (address, CredentialInfo {
+        name: CredentialName::EthPersonalSign,
+        hrp: None,
+        extension: None,
+        address: None,
+    });
+    let action = Action::new(&ActionMsg::Freeze {}, ActionDerivation::default()).unwrap();
+    let create = SessionActionMsg::<ActionMsg>::CreateSession(CreateSession {
+        allowed_actions: AllowedActions::Include(vec![action]),
+        session_info: info,
+        policy: None,
+        mode: None,
+        label: None,
+    });
+    let res = handle_session_action(
+        mocks.as_mut(), &env, &person_info(ALICE_ADDR), 
+        SessionAction { msg: create, signed: None }, 
+        None, 
+        |_, _, _, _| Ok::<Response, WasmAuthError>(Response::new())
+    ).unwrap();
+    let key = res.attributes.iter().find(|a| a.key == "session_key").unwrap().value.clone();
+
+    let messages = vec![to_json_string(&ActionMsg::Freeze {}).unwrap()];
+    let typed = TypedDataToSign::new(&env, 0, messages.clone());
+
+    // signed by someone else
+    assert!(run_signed(mocks.as_mut(), &env, &key, sign_typed_data(&other, &typed).unwrap()).is_err());
+
+    // signed for another contract
+    let mut wrong = typed.clone();
+    wrong.domain.verifying_contract = BOB_ADDR.to_string();
+    let err = run_signed(mocks.as_mut(), &env, &key, sign_typed_data(&signer, &wrong).unwrap()).unwrap_err();
+    assert_eq!(err, EnvelopeError::Contract.into());
+
+    // signed for another chain
+    let mut wrong = typed.clone();
+    wrong.domain.chain_id = "stargaze-1".to_string();
+    let err = run_signed(mocks.as_mut(), &env, &key, sign_typed_data(&signer, &wrong).unwrap()).unwrap_err();
+    assert_eq!(err, EnvelopeError::ChainId.into());
+
+    let signed = sign_typed_data(&signer, &typed).unwrap();
+    run_signed(mocks.as_mut(), &env, &key, signed.clone()).unwrap();
+
+    // can't be replayed
+    assert!(run_signed(mocks.as_mut(), &env, &key, signed).is_err());
+
+    let typed = TypedDataToSign::new(&env, 1, messages);
+    run_signed(mocks.as_mut(), &env, &key, sign_typed_data(&signer, &typed).unwrap()).unwrap();
+}
+
+
+
+#[test]
+fn typed_data_domain_works() {
+    let env = get_mock_env();
+    let typed = TypedDataToSign::new(&env, 0, vec![]);
+
+    // the chain and the contract are passed to the wallets in the domain only
+    let json = to_json_string(&typed).unwrap();
+    assert!(json.contains(&format!("\"chainId\":\"{}\"", env.block.chain_id)));
+    assert!(json.contains(&format!("\"verifyingContract\":\"{}\"", env.contract.address)));
+    assert!(!json.contains("chain_id") && !json.contains("contract_address"));
+
+    // and change the digest
+    let mut other = typed.clone();
+    other.domain.chain_id = "stargaze-1".to_string();
+    assert_ne!(typed.digest(), other.digest());
+}
+
+
+
+#[test]
+fn typed_data_credentials_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let signer = test_signers().eth;
+    let address = signer.address();
+    let other = EthSigner::from_seed(b"other").unwrap();
+    save_eth(&mut mocks.storage, &address);
+
+    let messages = vec![to_json_string(&ActionMsg::Freeze {}).unwrap()];
+    let typed = TypedDataToSign::new(&env, 0, messages.clone());
+
+    // signed by someone else on behalf of the stored address
+    let forged = typed_cred(&other, &address, &typed);
+    assert!(verify_cred_actions(&mocks.api, &mut mocks.storage, &env, forged, Some(messages.clone())).is_err());
+
+    // signed for other messages
+    let wrong = vec![to_json_string(&ActionMsg::Purge {}).unwrap()];
+    let cred = typed_cred(&signer, &address, &typed);
+    let err = verify_cred_actions(&mocks.api, &mut mocks.storage, &env, cred.clone(), Some(wrong)).unwrap_err();
+    assert_eq!(err, EnvelopeError::Message(0).into());
+    assert_eq!(ACCOUNT_NUMBER.may_load(&mocks.storage).unwrap(), None);
+
+    verify_cred_actions(&mocks.api, &mut mocks.storage, &env, cred.clone(), Some(messages.clone())).unwrap();
+    assert_eq!(ACCOUNT_NUMBER.load(&mocks.storage).unwrap(), 1);
+
+    // can't be replayed
+    assert!(verify_cred_actions(&mocks.api, &mut mocks.storage, &env, cred, Some(messages.clone())).is_err());
+
+    let next = typed_cred(&signer, &address, &TypedDataToSign::new(&env, 1, messages.clone()));
+    verify_cred_actions(&mocks.api, &mut mocks.storage, &env, next, Some(messages.clone())).unwrap();
+    assert_eq!(ACCOUNT_NUMBER.load(&mocks.storage).unwrap(), 2);
+
+    // typed data with validity bounds
+    let height = env.block.height;
+    let stale = ValidityBounds { valid_after: None, valid_until: Some(BlockBound::Height(height - 1)) };
+    let typed = TypedDataToSign::with_bounds(&env, 2, messages.clone(), &stale);
+    let cred = typed_cred(&signer, &address, &typed);
+    let err = verify_cred_actions(&mocks.api, &mut mocks.storage, &env, cred, Some(messages.clone())).unwrap_err();
+    assert_eq!(err, ValidityError::Stale.into());
+
+    let bounds = ValidityBounds { 
+        valid_after: Some(BlockBound::Time(Timestamp::from_seconds(env.block.time.seconds()))), 
+        valid_until: Some(BlockBound::Height(height)) 
+    };
+    let typed = TypedDataToSign::with_bounds(&env, 2, messages.clone(), &bounds);
+    assert_eq!(typed.message.bounds.as_ref().unwrap().validity().unwrap(), bounds);
+
+    // the bounds are a part of the signed digest
+    let mut unbounded = typed_cred(&signer, &address, &typed);
+    if let Credential::EthPersonalSign(eth) = &mut unbounded {
+        let mut changed = typed.clone();
+        changed.message.bounds = Some(TypedBounds::default());
+        eth.message = to_json_binary(&changed).unwrap();
+    }
+    assert!(verify_cred_actions(&mocks.api, &mut mocks.storage, &env, unbounded, Some(messages.clone())).is_err());
+
+    let cred = typed_cred(&signer, &address, &typed);
+    verify_cred_actions(&mocks.api, &mut mocks.storage, &env, cred, Some(messages)).unwrap();
+    assert_eq!(ACCOUNT_NUMBER.load(&mocks.storage).unwrap(), 3);
+}
+
+
+
+#[test]
+fn typed_data_signed_msgs_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let signer = test_signers().eth;
+    let address = signer.address();
+    let other = EthSigner::from_seed(b"other").unwrap();
+    save_eth(&mut mocks.storage, &address);
+
+    let actions = vec![ActionMsg::Freeze {}];
+    let messages = actions.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+    let typed = TypedDataToSign::new(&env, 0, messages.clone());
+
+    verify_signed(mocks.as_ref(), &env, actions.clone(), sign_typed_data(&signer, &typed).unwrap()).unwrap();
+    assert!(verify_signed(mocks.as_ref(), &env, actions.clone(), sign_typed_data(&other, &typed).unwrap()).is_err());
+    assert!(verify_signed(mocks.as_ref(), &env, vec![ActionMsg::Purge {}], sign_typed_data(&signer, &typed).unwrap()).is_err());
+
+    let signed = sign_typed_data(&signer, &typed).unwrap();
+    let mut deps = mocks.as_mut();
+    assert!(verify_signed_actions(&mut deps, &env, actions.clone(), sign_typed_data(&other, &typed).unwrap()).is_err());
+    verify_signed_actions(&mut deps, &env, actions.clone(), signed.clone()).unwrap();
+    assert_eq!(ACCOUNT_NUMBER.load(deps.storage).unwrap(), 1);
+
+    // can't be replayed
+    assert!(verify_signed_actions(&mut deps, &env, actions.clone(), signed).is_err());
+
+    let typed = TypedDataToSign::new(&env, 1, messages);
+    verify_signed_actions(&mut deps, &env, actions, sign_typed_data(&signer, &typed).unwrap()).unwrap();
+    assert_eq!(ACCOUNT_NUMBER.load(deps.storage).unwrap(), 2);
+}
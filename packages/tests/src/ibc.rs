@@ -0,0 +1,150 @@
+use saa_wasm::{
+    allow_ibc_channel, disallow_ibc_channel, ibc_receive_response, save_credentials, verify_ibc_packet, 
+    testing::{sign_typed_data, CredentialSigner, DataSigner, EthSigner}, 
+    SignedActionsAck, SignedActionsPacket, TypedDataToSign
+};
+use smart_account_auth::{
+    msgs::MsgDataToSign, CheckOption, CredentialData, CredentialsWrapper, ReplayParams
+};
+use types::{
+    stores::ACCOUNT_NUMBER,
+    wasm::{from_json, testing::mock_ibc_packet_recv, to_json_binary, to_json_string, DepsMut, Env, Uint64}
+};
+
+use crate::utils::{get_mock_deps, get_mock_env, person_info, test_signers, ALICE_ADDR};
+
+
+const CHANNEL : &str = "channel-7";
+
+
+fn signed_packet(
+    signer: &EthSigner,
+    chain_id: &str,
+    contract: &str,
+    nonce: u64,
+    msgs: Vec<String>,
+) -> SignedActionsPacket {
+    let envelope = MsgDataToSign {
+        chain_id: chain_id.to_string(),
+        contract_address: contract.to_string(),
+        messages: msgs.iter().map(|m| to_json_string(m).unwrap()).collect(),
+        nonce: Uint64::new(nonce),
+    };
+    SignedActionsPacket {
+        msgs,
+        signed: signer.signed_data(to_json_binary(&envelope).unwrap()).unwrap(),
+    }
+}
+
+
+/// Stores the Ethereum credential with an envelope signed at nonce 0
+fn save_eth(deps: DepsMut, env: &Env, signer: &EthSigner) {
+    let message = to_json_binary(&MsgDataToSign {
+        chain_id: env.block.chain_id.clone(),
+        contract_address: env.contract.address.to_string(),
+        messages: vec![],
+        nonce: Uint64::zero(),
+    }).unwrap();
+    let cred = signer.credential(message).unwrap();
+    let verified = CredentialData::new(vec![cred], None)
+        .verify(deps.as_ref(), env, &person_info(ALICE_ADDR), ReplayParams::new(0, CheckOption::Nothing))
+        .unwrap();
+    save_credentials(deps.storage, &verified).unwrap();
+}
+
+
+
+#[test]
+fn ibc_signed_actions_work() {
+    let mut mocks = get_mock_deps();
+    let env = get_mock_env();
+    let key = test_signers().eth;
+    let chain_id = env.block.chain_id.clone();
+    let contract = env.contract.address.to_string();
+
+    save_eth(mocks.as_mut(), &env, &key);
+    assert_eq!(ACCOUNT_NUMBER.load(mocks.as_ref().storage).unwrap(), 1);
+
+    let msgs = vec!["transfer".to_string(), "stake".to_string()];
+    let packet = signed_packet(&key, &chain_id, &contract, 1, msgs.clone());
+    let recv = mock_ibc_packet_recv(CHANNEL, &packet).unwrap();
+
+    // channel not in the allowlist
+    let err = verify_ibc_packet::<String>(&mut mocks.as_mut(), &env, &recv).unwrap_err();
+    assert!(err.to_string().contains(CHANNEL));
+
+    // packets from other channels are still rejected
+    allow_ibc_channel(mocks.as_mut().storage, "channel-0").unwrap();
+    assert!(verify_ibc_packet::<String>(&mut mocks.as_mut(), &env, &recv).is_err());
+    allow_ibc_channel(mocks.as_mut().storage, CHANNEL).unwrap();
+
+    // signed for the chain that has sent the packet
+    let foreign = signed_packet(&key, "osmosis-1", &contract, 1, msgs.clone());
+    let foreign_recv = mock_ibc_packet_recv(CHANNEL, &foreign).unwrap();
+    assert!(verify_ibc_packet::<String>(&mut mocks.as_mut(), &env, &foreign_recv).is_err());
+
+    // messages of the packet differ from the signed ones
+    let mut tampered = packet.clone();
+    tampered.msgs = vec!["transfer".to_string(), "withdraw".to_string()];
+    let tampered_recv = mock_ibc_packet_recv(CHANNEL, &tampered).unwrap();
+    assert!(verify_ibc_packet::<String>(&mut mocks.as_mut(), &env, &tampered_recv).is_err());
+
+    // valid packet
+    let received = verify_ibc_packet::<String>(&mut mocks.as_mut(), &env, &recv).unwrap();
+    assert_eq!(received, msgs);
+    assert_eq!(ACCOUNT_NUMBER.load(mocks.as_ref().storage).unwrap(), 2);
+
+    let res = ibc_receive_response(Ok::<_, String>(received));
+    let ack : SignedActionsAck = from_json(res.acknowledgement.unwrap()).unwrap();
+    assert!(ack.is_success());
+
+    // relayed again
+    let err = verify_ibc_packet::<String>(&mut mocks.as_mut(), &env, &recv).unwrap_err().to_string();
+    let res = ibc_receive_response(Err::<(), _>(err.clone()));
+    let ack : SignedActionsAck = from_json(res.acknowledgement.unwrap()).unwrap();
+    assert_eq!(ack, SignedActionsAck::Error(err));
+
+    // removed from the allowlist
+    disallow_ibc_channel(mocks.as_mut().storage, CHANNEL);
+    let next = signed_packet(&key, &chain_id, &contract, 2, msgs);
+    let next_recv = mock_ibc_packet_recv(CHANNEL, &next).unwrap();
+    assert!(verify_ibc_packet::<String>(&mut mocks.as_mut(), &env, &next_recv).is_err());
+    assert_eq!(ACCOUNT_NUMBER.load(mocks.as_ref().storage).unwrap(), 2);
+}
+
+
+
+#[test]
+fn ibc_typed_data_packets_work() {
+    let mut mocks = get_mock_deps();
+    let env = get_mock_env();
+    let signers = test_signers();
+    save_eth(mocks.as_mut(), &env, &signers.eth);
+    allow_ibc_channel(mocks.as_mut().storage, CHANNEL).unwrap();
+
+    let msgs = vec!["transfer".to_string(), "stake".to_string()];
+    let messages = msgs.iter().map(|m| to_json_string(m).unwrap()).collect::<Vec<String>>();
+    let packet = |signer: &EthSigner, typed: &TypedDataToSign| SignedActionsPacket {
+        msgs: msgs.clone(),
+        signed: sign_typed_data(signer, typed).unwrap(),
+    };
+
+    // signed by another wallet
+    let typed = TypedDataToSign::new(&env, 1, messages.clone());
+    let other = EthSigner::from_seed(b"other").unwrap();
+    let recv = mock_ibc_packet_recv(CHANNEL, &packet(&other, &typed)).unwrap();
+    assert!(verify_ibc_packet::<String>(&mut mocks.as_mut(), &env, &recv).is_err());
+
+    // signed for other messages
+    let wrong = TypedDataToSign::new(&env, 1, vec![to_json_string("withdraw").unwrap()]);
+    let recv = mock_ibc_packet_recv(CHANNEL, &packet(&signers.eth, &wrong)).unwrap();
+    assert!(verify_ibc_packet::<String>(&mut mocks.as_mut(), &env, &recv).is_err());
+
+    let recv = mock_ibc_packet_recv(CHANNEL, &packet(&signers.eth, &typed)).unwrap();
+    let received = verify_ibc_packet::<String>(&mut mocks.as_mut(), &env, &recv).unwrap();
+    assert_eq!(received, msgs);
+    assert_eq!(ACCOUNT_NUMBER.load(mocks.as_ref().storage).unwrap(), 2);
+
+    // relayed again
+    assert!(verify_ibc_packet::<String>(&mut mocks.as_mut(), &env, &recv).is_err());
+}
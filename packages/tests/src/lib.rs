@@ -7,4 +7,12 @@ mod utils;
 
 
 #[cfg(test)]
-mod storage;
\ No newline at end of file
+mod storage;
+#[cfg(test)]
+mod sessions;
+#[cfg(test)]
+mod eip712;
+#[cfg(test)]
+mod ibc;
+#[cfg(test)]
+mod client;
@@ -0,0 +1,1346 @@
+use saa_wasm::{
+    testing::{signed_data_msg, AccountContext, CredentialSigner, DataSigner, PasskeySigner, TestSigners},
+    export_auth_state, handle_session_action, verify_signed, handle_session_query, parse_session_events, 
+    prune_expired_sessions, restore_credentials, save_session_config, unwrap_adr036, wrap_adr036,
+    ArgConstraint, AuthStateExport, ConstraintRule, CosmosMsgPolicy, CreateSession, CreateSessionFrom, 
+    CreateSubSession, CreationMode, ExpiredKeyPolicy, MsgToDerive, Period, PeriodLimit, RateLimit, RevokeKeyMsg, 
+    RevokeSessionsMsg, SessionAction, SessionActionMsg, SessionAdmins, SessionConfig, SessionEvent, 
+    SessionPolicy, SessionQueryMsg, SessionResponse, SpendingLimit, StdSignDoc, UpdateSessionMsg, 
+    verify_session_native, WasmAuthError, WithSessionMsg
+};
+use smart_account_auth::{
+    msgs::{Action, ActionDerivation, AllowedActions, MsgDataToSign, SignedDataMsg}, 
+    types::exts::InfoExtension,
+    CredentialInfo, Expiration, SessionInfo, Verifiable
+};
+use types::{
+    errors::{EnvelopeError, RestoreError, SessionCreationError, SessionError, SessionPolicyError, SubSessionError, ValidityError}, 
+    stores::{ACCOUNT_NUMBER, CREDENTIAL_INFOS, HAS_NATIVES, PRIMARY_ID, SESSIONS}};
+use types::wasm::{
+    coin, coins, from_json, to_json_binary, to_json_string, AnyMsg, BankMsg, Binary, CosmosMsg, 
+    Deps, DepsMut, DistributionMsg, Env, Event, IbcMsg, MessageInfo, QuerierWrapper, Response, 
+    StakingMsg, Uint128, WasmMsg
+};
+
+use crate::{
+    types::{ActionMsg, QueryMsg}, 
+    utils::{
+        get_account_deps, get_mock_deps, get_mock_env, native_grantee, person_info, session_info, test_signers, 
+        CountingApi, ALICE_ADDR, BOB_ADDR, EVE_ADDR, MESSAGE_TEXT, SIGNERS_SEED, 
+        ED25519_VERIFY_GAS, SECP256K1_RECOVER_GAS, SECP256K1_VERIFY_GAS, SECP256R1_VERIFY_GAS
+    }
+};
+
+
+
+fn run(
+    deps: DepsMut, 
+    env: &Env, 
+    info: &MessageInfo, 
+    msg: SessionActionMsg<ActionMsg>
+) -> Result<Response, WasmAuthError> {
+    run_as(deps, env, info, msg, None)
+}
+
+
+fn run_as(
+    deps: DepsMut, 
+    env: &Env, 
+    info: &MessageInfo, 
+    msg: SessionActionMsg<ActionMsg>,
+    admin: Option<String>,
+) -> Result<Response, WasmAuthError> {
+    handle_session_action(
+        deps, env, info, 
+        SessionAction { msg, signed: None }, 
+        admin, 
+        |_, _, _, _| Ok(Response::new())
+    )
+}
+
+
+fn query_session(deps: Deps, env: &Env, key: &str) -> SessionResponse {
+    let res = handle_session_query::<QueryMsg>(
+        deps.api, deps.storage, env, 
+        SessionQueryMsg::Session { session_key: key.to_string() }
+    ).unwrap();
+    from_json(res).unwrap()
+}
+
+
+fn attr(res: &Response, key: &str) -> String {
+    res.attributes
+        .iter()
+        .find(|a| a.key == key)
+        .map(|a| a.value.clone())
+        .unwrap()
+}
+
+
+fn send_msg(amount: u128, denom: &str) -> ActionMsg {
+    ActionMsg::Execute { 
+        msgs: vec![CosmosMsg::Bank(BankMsg::Send { 
+            to_address: EVE_ADDR.to_string(), 
+            amount: coins(amount, denom) 
+        })] 
+    }
+}
+
+
+fn create_msg(policy: Option<SessionPolicy>) -> SessionActionMsg<ActionMsg> {
+    create_msg_with(session_info(), policy)
+}
+
+
+fn create_msg_with(session_info: SessionInfo, policy: Option<SessionPolicy>) -> SessionActionMsg<ActionMsg> {
+    let action = Action::new(&send_msg(0, "ustars"), ActionDerivation::default()).unwrap();
+    SessionActionMsg::CreateSession(CreateSession {
+        allowed_actions: AllowedActions::Include(vec![action]),
+        session_info,
+        policy,
+        mode: None,
+        label: None,
+    })
+}
+
+
+fn expiring_info(grantee: &str, expiration: Expiration) -> SessionInfo {
+    let mut info = session_info();
+    info.grantee = native_grantee(grantee);
+    info.expiration = Some(expiration);
+    info
+}
+
+
+fn with_key(key: &str, msgs: ActionMsg) -> SessionActionMsg<ActionMsg> {
+    SessionActionMsg::WithSessionKey(WithSessionMsg {
+        session_key: key.to_string(),
+        msgs,
+    })
+}
+
+
+/// Key of a new session granted to the credential of the signer
+fn signed_session(deps: DepsMut, env: &Env, signer: &dyn DataSigner) -> String {
+    let cred = signer.credential(Binary::from(b"grantee")).unwrap();
+    let info = cred.verify(deps.as_ref()).unwrap();
+    let session_info = SessionInfo { expiration: None, granter: None, grantee: (cred.id(), info) };
+    let res = run(deps, env, &person_info(ALICE_ADDR), create_msg_with(session_info, None)).unwrap();
+    attr(&res, "session_key")
+}
+
+
+/// Raw JSON data signed by the signer as it is
+fn sign_raw(signer: &dyn DataSigner, data: &str) -> SignedDataMsg {
+    signer.signed_data(Binary::from(data.as_bytes())).unwrap()
+}
+
+
+
+#[test]
+fn spending_limits_work() {
+    let mut mocks = get_account_deps();
+    let mut env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+
+    let policy = SessionPolicy {
+        spending_limits: vec![SpendingLimit {
+            denom: "ustars".to_string(),
+            total: Some(Uint128::new(300)),
+            per_period: Some(PeriodLimit { amount: Uint128::new(150), period: Period::Blocks(10) }),
+        }],
+        ..Default::default()
+    };
+
+    let res = run(mocks.as_mut(), &env, &admin, create_msg(Some(policy))).unwrap();
+    let key = attr(&res, "session_key");
+
+    run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(100, "ustars"))).unwrap();
+
+    // over the limit of the current period
+    let err = run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(100, "ustars"))).unwrap_err();
+    assert_eq!(err, SessionPolicyError::SpendingLimit("ustars".to_string()).into());
+
+    // denoms without a limit are not restricted
+    run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1000, "uatom"))).unwrap();
+
+    // the period has been reset
+    env.block.height += 10;
+    run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(100, "ustars"))).unwrap();
+
+    env.block.height += 10;
+    run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(100, "ustars"))).unwrap();
+
+    // the total cap is reached despite the new period
+    env.block.height += 10;
+    let err = run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(50, "ustars"))).unwrap_err();
+    assert!(matches!(err, WasmAuthError::Policy(SessionPolicyError::SpendingLimit(_))));
+}
+
+
+
+#[test]
+fn spending_limits_cover_every_msg() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+
+    let policy = SessionPolicy {
+        spending_limits: vec![SpendingLimit {
+            denom: "ustars".to_string(),
+            total: Some(Uint128::new(100)),
+            per_period: None,
+        }],
+        ..Default::default()
+    };
+    let res = run(mocks.as_mut(), &env, &admin, create_msg(Some(policy))).unwrap();
+    let key = attr(&res, "session_key");
+    let execute = |msg: CosmosMsg| with_key(&key, ActionMsg::Execute { msgs: vec![msg] });
+    let over = coin(1000, "ustars");
+
+    let counted = vec![
+        CosmosMsg::Wasm(WasmMsg::Instantiate2 { 
+            admin: None, 
+            code_id: 1, 
+            label: "drain".to_string(), 
+            msg: Binary::from(b"{}"), 
+            funds: vec![over.clone()], 
+            salt: Binary::from(b"salt") 
+        }),
+        CosmosMsg::Ibc(IbcMsg::Transfer { 
+            channel_id: "channel-0".to_string(), 
+            to_address: EVE_ADDR.to_string(), 
+            amount: over.clone(), 
+            timeout: env.block.time.plus_seconds(60).into(), 
+            memo: None 
+        }),
+        CosmosMsg::Staking(StakingMsg::Delegate { validator: EVE_ADDR.to_string(), amount: over.clone() }),
+        CosmosMsg::Distribution(DistributionMsg::FundCommunityPool { amount: vec![over.clone()] }),
+    ];
+    for msg in counted {
+        let err = run(mocks.as_mut(), &env, &bob, execute(msg)).unwrap_err();
+        assert_eq!(err, SessionPolicyError::SpendingLimit("ustars".to_string()).into());
+    }
+
+    // the funds of the opaque messages can't be metered
+    let any = CosmosMsg::Any(AnyMsg { type_url: "/cosmos.bank.v1beta1.MsgSend".to_string(), value: Binary::default() });
+    let err = run(mocks.as_mut(), &env, &bob, execute(any)).unwrap_err();
+    assert_eq!(err, SessionPolicyError::UnmeteredMsg("any".to_string()).into());
+    #[allow(deprecated)]
+    let stargate = CosmosMsg::Stargate { type_url: "/cosmos.bank.v1beta1.MsgSend".to_string(), value: Binary::default() };
+    let err = run(mocks.as_mut(), &env, &bob, execute(stargate)).unwrap_err();
+    assert_eq!(err, SessionPolicyError::UnmeteredMsg("stargate".to_string()).into());
+    let withdraw = CosmosMsg::Distribution(DistributionMsg::SetWithdrawAddress { address: EVE_ADDR.to_string() });
+    let err = run(mocks.as_mut(), &env, &bob, execute(withdraw)).unwrap_err();
+    assert!(matches!(err, WasmAuthError::Policy(SessionPolicyError::UnmeteredMsg(_))));
+
+    // within the limit
+    let delegate = CosmosMsg::Staking(StakingMsg::Delegate { validator: EVE_ADDR.to_string(), amount: coin(60, "ustars") });
+    run(mocks.as_mut(), &env, &bob, execute(delegate.clone())).unwrap();
+    assert!(run(mocks.as_mut(), &env, &bob, execute(delegate)).is_err());
+    let undelegate = CosmosMsg::Staking(StakingMsg::Undelegate { validator: EVE_ADDR.to_string(), amount: over });
+    run(mocks.as_mut(), &env, &bob, execute(undelegate)).unwrap();
+}
+
+
+
+#[test]
+fn rate_limits_work() {
+    let mut mocks = get_account_deps();
+    let mut env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+
+    let policy = SessionPolicy {
+        rate_limit: Some(RateLimit { max_actions: 2, window: Period::Seconds(60) }),
+        ..Default::default()
+    };
+
+    let res = run(mocks.as_mut(), &env, &admin, create_msg(Some(policy))).unwrap();
+    let key = attr(&res, "session_key");
+    assert_eq!(query_session(mocks.as_ref(), &env, &key).remaining_actions, Some(2));
+
+    run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).unwrap();
+    env.block.time = env.block.time.plus_seconds(30);
+    run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).unwrap();
+    assert_eq!(query_session(mocks.as_ref(), &env, &key).remaining_actions, Some(0));
+
+    // no actions left in the window
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).is_err());
+
+    // the first action left the rolling window
+    env.block.time = env.block.time.plus_seconds(30);
+    assert_eq!(query_session(mocks.as_ref(), &env, &key).remaining_actions, Some(1));
+    run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).unwrap();
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).is_err());
+}
+
+
+
+#[test]
+fn expired_sessions_pruning_works() {
+    let mut mocks = get_account_deps();
+    let mut env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+
+    let by_height = expiring_info(BOB_ADDR, Expiration::AtHeight(env.block.height + 5));
+    let by_time = expiring_info(EVE_ADDR, Expiration::AtTime(env.block.time.plus_seconds(50)));
+
+    let res = run(mocks.as_mut(), &env, &admin, create_msg_with(by_height, None)).unwrap();
+    let height_key = attr(&res, "session_key");
+    let res = run(mocks.as_mut(), &env, &admin, create_msg_with(by_time, None)).unwrap();
+    let time_key = attr(&res, "session_key");
+    let never = expiring_info(ALICE_ADDR, Expiration::Never {});
+    let res = run(mocks.as_mut(), &env, &admin, create_msg_with(never, None)).unwrap();
+    let never_key = attr(&res, "session_key");
+
+    // nothing has expired yet
+    assert!(prune_expired_sessions(mocks.as_mut().storage, &env, 10).unwrap().is_empty());
+
+    env.block.height += 5;
+    let pruned = prune_expired_sessions(mocks.as_mut().storage, &env, 10).unwrap();
+    assert_eq!(pruned, vec![height_key.clone()]);
+    assert!(!SESSIONS.has(mocks.as_ref().storage, height_key));
+
+    // automatic pruning during any session action
+    save_session_config(mocks.as_mut().storage, &SessionConfig { auto_prune: Some(10), ..Default::default() }).unwrap();
+    env.block.time = env.block.time.plus_seconds(50);
+    let res = run(mocks.as_mut(), &env, &admin, create_msg(None)).unwrap();
+    assert_eq!(attr(&res, "pruned_sessions"), "1");
+    assert!(!SESSIONS.has(mocks.as_ref().storage, time_key));
+    assert!(SESSIONS.has(mocks.as_ref().storage, never_key));
+}
+
+
+
+#[test]
+fn updating_sessions_works() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+
+    let res = run(mocks.as_mut(), &env, &admin, create_msg(None)).unwrap();
+    let key = attr(&res, "session_key");
+
+    let freeze = Action::new(&ActionMsg::Freeze {}, ActionDerivation::default()).unwrap();
+    let expiration = Expiration::AtHeight(env.block.height + 100);
+    let update = || SessionActionMsg::UpdateSession(UpdateSessionMsg {
+        session_key: key.clone(),
+        expiration: Some(expiration.clone()),
+        allowed_actions: Some(AllowedActions::Include(vec![freeze.clone()])),
+    });
+
+    // only the granter can update
+    assert!(run_as(mocks.as_mut(), &env, &admin, update(), Some(EVE_ADDR.to_string())).is_err());
+
+    // a session that has already been used with signed payloads
+    let mut used = SESSIONS.load(mocks.as_ref().storage, key.clone()).unwrap();
+    used.nonce = 3;
+    SESSIONS.save(mocks.as_mut().storage, key.clone(), &used).unwrap();
+
+    run(mocks.as_mut(), &env, &admin, update()).unwrap();
+
+    let res = query_session(mocks.as_ref(), &env, &key);
+    // the nonce never goes back so the old payloads can't be replayed
+    assert!(res.session.nonce >= 3);
+    assert_eq!(res.session.expiration, expiration);
+    assert_eq!(res.session.actions, AllowedActions::Include(vec![freeze]));
+
+    // the key stays the same but the actions have changed
+    run(mocks.as_mut(), &env, &bob, with_key(&key, ActionMsg::Freeze {})).unwrap();
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).is_err());
+}
+
+
+
+#[test]
+fn bulk_revocation_works() {
+    let mut mocks = get_account_deps();
+    let mut env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let revoke = |msg| SessionActionMsg::RevokeSessions(msg);
+
+    let expiring = expiring_info(EVE_ADDR, Expiration::AtHeight(env.block.height + 5));
+    run(mocks.as_mut(), &env, &admin, create_msg_with(expiring, None)).unwrap();
+    let res = run(mocks.as_mut(), &env, &admin, create_msg(None)).unwrap();
+    let bob_key = attr(&res, "session_key");
+    let mut alice_info = session_info();
+    alice_info.grantee = native_grantee(ALICE_ADDR);
+    run(mocks.as_mut(), &env, &admin, create_msg_with(alice_info, None)).unwrap();
+
+    // unknown keys
+    let err = run(mocks.as_mut(), &env, &admin, revoke(RevokeSessionsMsg::Keys(vec!["unknown".to_string()])));
+    assert!(err.is_err());
+
+    // not granted by the caller and not expired
+    let other = Some(EVE_ADDR.to_string());
+    let res = run_as(mocks.as_mut(), &env, &admin, revoke(RevokeSessionsMsg::All {}), other.clone()).unwrap();
+    assert_eq!(attr(&res, "revoked"), "0");
+    assert!(run_as(mocks.as_mut(), &env, &admin, revoke(RevokeSessionsMsg::Keys(vec![bob_key.clone()])), other.clone()).is_err());
+
+    // anyone can remove the expired ones
+    env.block.height += 5;
+    let res = run_as(mocks.as_mut(), &env, &admin, revoke(RevokeSessionsMsg::All {}), other).unwrap();
+    assert_eq!(attr(&res, "revoked"), "1");
+
+    let res = run(mocks.as_mut(), &env, &admin, revoke(RevokeSessionsMsg::Grantee(BOB_ADDR.to_string()))).unwrap();
+    assert_eq!(attr(&res, "revoked"), "1");
+    assert!(!SESSIONS.has(mocks.as_ref().storage, bob_key));
+
+    let granter = env.contract.address.to_string();
+    let res = run(mocks.as_mut(), &env, &admin, revoke(RevokeSessionsMsg::Granter(granter))).unwrap();
+    assert_eq!(attr(&res, "revoked"), "1");
+}
+
+
+
+#[test]
+fn sub_sessions_work() {
+    let mut mocks = get_account_deps();
+    let mut env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+    let eve = person_info(EVE_ADDR);
+
+    let height = env.block.height;
+    let parent_info = expiring_info(BOB_ADDR, Expiration::AtHeight(height + 100));
+    let res = run(mocks.as_mut(), &env, &admin, create_msg_with(parent_info, None)).unwrap();
+    let parent_key = attr(&res, "session_key");
+
+    let execute = Action::new(&send_msg(0, "ustars"), ActionDerivation::default()).unwrap();
+    let freeze = Action::new(&ActionMsg::Freeze {}, ActionDerivation::default()).unwrap();
+    let sub_session = |actions: Vec<Action>, expiration: u64| SessionActionMsg::CreateSubSession(CreateSubSession {
+        parent_key: parent_key.clone(),
+        allowed_actions: AllowedActions::Include(actions),
+        session_info: expiring_info(EVE_ADDR, Expiration::AtHeight(height + expiration)),
+        policy: None,
+    });
+
+    // only the grantee of the parent can create sub-sessions
+    assert!(run(mocks.as_mut(), &env, &eve, sub_session(vec![execute.clone()], 50)).is_err());
+    // can't outlive the parent
+    let err = run(mocks.as_mut(), &env, &bob, sub_session(vec![execute.clone()], 200)).unwrap_err();
+    assert_eq!(err, SubSessionError::OutlivesParent.into());
+    // can't do what the parent can't
+    let err = run(mocks.as_mut(), &env, &bob, sub_session(vec![freeze.clone()], 50)).unwrap_err();
+    assert_eq!(err, SubSessionError::ExceedsParent.into());
+
+    let res = run(mocks.as_mut(), &env, &bob, sub_session(vec![execute.clone()], 50)).unwrap();
+    let child_key = attr(&res, "session_key");
+    assert_eq!(attr(&res, "parent_key"), parent_key);
+
+    run(mocks.as_mut(), &env, &eve, with_key(&child_key, send_msg(1, "ustars"))).unwrap();
+
+    // one live sub-session per grantee of the same parent
+    let err = run(mocks.as_mut(), &env, &bob, sub_session(vec![execute.clone()], 50)).unwrap_err();
+    assert_eq!(err, SubSessionError::AlreadyExists(child_key.clone()).into());
+    assert!(run(mocks.as_mut(), &env, &bob, sub_session(vec![execute.clone()], 40)).is_err());
+
+    let update = |key: &String, expiration: Option<u64>, actions: Option<Vec<Action>>| 
+        SessionActionMsg::UpdateSession(UpdateSessionMsg {
+            session_key: key.clone(),
+            expiration: expiration.map(|e| Expiration::AtHeight(height + e)),
+            allowed_actions: actions.map(AllowedActions::Include),
+        });
+
+    // the admin can't widen the child past its parent
+    let err = run(mocks.as_mut(), &env, &admin, update(&child_key, Some(200), None)).unwrap_err();
+    assert_eq!(err, SubSessionError::OutlivesParent.into());
+    let widened = Some(vec![execute.clone(), freeze.clone()]);
+    let err = run(mocks.as_mut(), &env, &admin, update(&child_key, None, widened)).unwrap_err();
+    assert_eq!(err, SubSessionError::ExceedsParent.into());
+    let res = run(mocks.as_mut(), &env, &admin, update(&child_key, Some(30), None)).unwrap();
+    assert_eq!(attr(&res, "revoked"), "0");
+
+    // widening the parent keeps the child
+    let widened = Some(vec![execute.clone(), freeze.clone()]);
+    let res = run(mocks.as_mut(), &env, &admin, update(&parent_key, None, widened)).unwrap();
+    assert_eq!(attr(&res, "revoked"), "0");
+    assert!(SESSIONS.has(mocks.as_ref().storage, child_key.clone()));
+
+    // shortening the parent revokes the child that outlives it
+    let res = run(mocks.as_mut(), &env, &admin, update(&parent_key, Some(10), None)).unwrap();
+    assert_eq!(attr(&res, "revoked"), "1");
+    assert!(!SESSIONS.has(mocks.as_ref().storage, child_key.clone()));
+    assert!(run(mocks.as_mut(), &env, &eve, with_key(&child_key, send_msg(1, "ustars"))).is_err());
+
+    // and so does taking away the actions of the child
+    let res = run(mocks.as_mut(), &env, &bob, sub_session(vec![execute.clone()], 5)).unwrap();
+    let child_key = attr(&res, "session_key");
+    let res = run(mocks.as_mut(), &env, &admin, update(&parent_key, None, Some(vec![freeze.clone()]))).unwrap();
+    assert_eq!(attr(&res, "revoked"), "1");
+    assert!(!SESSIONS.has(mocks.as_ref().storage, child_key));
+
+    // expired sub-sessions are replaced
+    run(mocks.as_mut(), &env, &admin, update(&parent_key, None, Some(vec![execute.clone()]))).unwrap();
+    run(mocks.as_mut(), &env, &bob, sub_session(vec![execute.clone()], 5)).unwrap();
+    env.block.height += 6;
+    let res = run(mocks.as_mut(), &env, &bob, sub_session(vec![execute.clone()], 9)).unwrap();
+    let child_key = attr(&res, "session_key");
+    assert_eq!(attr(&res, "nonce"), "0");
+    let child = SESSIONS.load(mocks.as_ref().storage, child_key.clone()).unwrap();
+    assert_eq!(child.expiration, Expiration::AtHeight(height + 9));
+    run(mocks.as_mut(), &env, &eve, with_key(&child_key, send_msg(1, "ustars"))).unwrap();
+
+    // expired sub-sessions are revoked when used
+    env.block.height += 4;
+    let res = run(mocks.as_mut(), &env, &eve, with_key(&child_key, send_msg(1, "ustars"))).unwrap();
+    assert_eq!(attr(&res, "status"), "revoked");
+    assert!(!SESSIONS.has(mocks.as_ref().storage, child_key));
+
+    // revoking the parent removes all of its children
+    env.block.height -= 10;
+    let res = run(mocks.as_mut(), &env, &bob, sub_session(vec![execute], 5)).unwrap();
+    let child_key = attr(&res, "session_key");
+    let revoke = SessionActionMsg::RevokeSession(RevokeKeyMsg { session_key: parent_key.clone() });
+    run(mocks.as_mut(), &env, &admin, revoke).unwrap();
+    assert!(!SESSIONS.has(mocks.as_ref().storage, parent_key));
+    assert!(!SESSIONS.has(mocks.as_ref().storage, child_key));
+}
+
+
+
+#[test]
+fn argument_constraints_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+
+    let transfer = |id: &str, to: &str| ActionMsg::TransferToken { id: id.to_string(), to: to.to_string() };
+    let action = Action::new(&transfer("1", EVE_ADDR), ActionDerivation::default()).unwrap();
+
+    let policy = SessionPolicy {
+        constraints: vec![
+            ArgConstraint { path: "transfer_token.to".to_string(), rule: ConstraintRule::Equals(EVE_ADDR.to_string()) },
+            ArgConstraint { path: "transfer_token.id".to_string(), rule: ConstraintRule::OneOf(vec!["1".into(), "2".into()]) },
+            ArgConstraint { path: "mint_token.minter".to_string(), rule: ConstraintRule::Prefix("stars".into()) },
+        ],
+        ..Default::default()
+    };
+    let create = SessionActionMsg::CreateSession(CreateSession {
+        allowed_actions: AllowedActions::Include(vec![action]),
+        session_info: session_info(),
+        policy: Some(policy),
+        mode: None,
+        label: None,
+    });
+    let res = run(mocks.as_mut(), &env, &admin, create).unwrap();
+    let key = attr(&res, "session_key");
+
+    run(mocks.as_mut(), &env, &bob, with_key(&key, transfer("1", EVE_ADDR))).unwrap();
+    run(mocks.as_mut(), &env, &bob, with_key(&key, transfer("2", EVE_ADDR))).unwrap();
+
+    // wrong recipient
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, transfer("1", ALICE_ADDR))).is_err());
+    // token not in the list
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, transfer("3", EVE_ADDR))).is_err());
+}
+
+
+
+#[test]
+fn list_constraints_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+
+    let policy = SessionPolicy {
+        constraints: vec![
+            ArgConstraint { 
+                path: "execute.msgs.*.bank.send.amount.*.amount".to_string(), 
+                rule: ConstraintRule::MaxAmount(Uint128::new(100)) 
+            },
+            ArgConstraint { 
+                path: "execute.msgs.*.bank.send.to_address".to_string(), 
+                rule: ConstraintRule::Equals(EVE_ADDR.to_string()) 
+            },
+        ],
+        ..Default::default()
+    };
+    let res = run(mocks.as_mut(), &env, &admin, create_msg(Some(policy))).unwrap();
+    let key = attr(&res, "session_key");
+
+    let send = |to: &str, amount: Vec<types::wasm::Coin>| CosmosMsg::Bank(BankMsg::Send { 
+        to_address: to.to_string(), amount 
+    });
+    let execute = |msgs: Vec<CosmosMsg>| ActionMsg::Execute { msgs };
+
+    run(mocks.as_mut(), &env, &bob, with_key(&key, execute(vec![send(EVE_ADDR, coins(100, "ustars"))]))).unwrap();
+    run(mocks.as_mut(), &env, &bob, with_key(&key, execute(vec![
+        send(EVE_ADDR, coins(40, "ustars")), send(EVE_ADDR, coins(60, "ustars"))
+    ]))).unwrap();
+
+    let assert_violated = |res: Result<Response, WasmAuthError>| assert!(matches!(
+        res.unwrap_err(), WasmAuthError::Policy(SessionPolicyError::Constraint(_))
+    ));
+    // payload moved to the second message
+    assert_violated(run(mocks.as_mut(), &env, &bob, with_key(&key, execute(vec![
+        send(EVE_ADDR, coins(1, "ustars")), send(EVE_ADDR, coins(500, "ustars"))
+    ]))));
+    // extra coins of the same message
+    assert_violated(run(mocks.as_mut(), &env, &bob, with_key(&key, execute(vec![
+        send(EVE_ADDR, vec![coin(80, "ustars"), coin(80, "uatom")])
+    ]))));
+    // recipient of the second message
+    assert_violated(run(mocks.as_mut(), &env, &bob, with_key(&key, execute(vec![
+        send(EVE_ADDR, coins(1, "ustars")), send(BOB_ADDR, coins(1, "ustars"))
+    ]))));
+
+    // the shared verification path enforces the same rules
+    let session = SESSIONS.load(&mocks.storage, key.clone()).unwrap();
+    let msgs = execute(vec![send(EVE_ADDR, coins(1, "ustars")), send(EVE_ADDR, coins(500, "ustars"))]);
+    assert!(verify_session_native(mocks.as_ref(), BOB_ADDR, &key, &session, &msgs).is_err());
+    let msgs = execute(vec![send(EVE_ADDR, coins(50, "ustars"))]);
+    verify_session_native(mocks.as_ref(), BOB_ADDR, &key, &session, &msgs).unwrap();
+}
+
+
+
+#[test]
+fn cosmos_msg_policies_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+
+    let policy = SessionPolicy {
+        cosmos_msgs: Some(CosmosMsgPolicy {
+            allowed_contracts: Some(vec![EVE_ADDR.to_string()]),
+            allowed_execute_msgs: Some(vec!["execute".to_string(), "transfer".to_string()]),
+            forbidden: vec!["wasm.migrate".to_string(), "staking".to_string()],
+        }),
+        ..Default::default()
+    };
+    let res = run(mocks.as_mut(), &env, &admin, create_msg(Some(policy))).unwrap();
+    let key = attr(&res, "session_key");
+
+    let execute = |contract: &str, msg: types::wasm::Binary| ActionMsg::Execute {
+        msgs: vec![CosmosMsg::Wasm(WasmMsg::Execute { contract_addr: contract.to_string(), msg, funds: vec![] })]
+    };
+    let migrate = ActionMsg::Execute {
+        msgs: vec![CosmosMsg::Wasm(WasmMsg::Migrate { 
+            contract_addr: EVE_ADDR.to_string(), new_code_id: 1, msg: to_json_binary(&"{}").unwrap() 
+        })]
+    };
+    let transfer = to_json_binary(&ActionMsg::TransferToken { id: "1".into(), to: BOB_ADDR.into() }).unwrap();
+    let transfer_msg = to_json_binary(&types::wasm::Empty {}).unwrap();
+    let named = |name: &str| to_json_binary(&std::collections::BTreeMap::from([(name.to_string(), transfer_msg.clone())])).unwrap();
+
+    run(mocks.as_mut(), &env, &bob, with_key(&key, execute(EVE_ADDR, named("transfer")))).unwrap();
+    run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).unwrap();
+
+    // not allowed contract
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, execute(ALICE_ADDR, named("transfer")))).is_err());
+    // not allowed execute message
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, execute(EVE_ADDR, transfer))).is_err());
+    // forbidden kind
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, migrate.clone())).is_err());
+    // forbidden kind nested inside of an execute message
+    let nested = execute(EVE_ADDR, to_json_binary(&migrate).unwrap());
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, nested)).is_err());
+}
+
+
+
+#[test]
+fn create_session_from_many_msgs_works() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+
+    let create = SessionActionMsg::CreateSessionFromMsg(CreateSessionFrom {
+        msgs: vec![
+            MsgToDerive { message: ActionMsg::Freeze {}, method: None },
+            MsgToDerive { message: send_msg(1, "ustars"), method: Some(ActionDerivation::default()) },
+            MsgToDerive { message: ActionMsg::Freeze {}, method: None },
+        ],
+        derivation: None,
+        session_info: session_info(),
+        policy: None,
+        mode: None,
+        label: None,
+    });
+
+    let res = handle_session_action(
+        mocks.as_mut(), &env, &admin,
+        SessionAction { msg: create, signed: None },
+        None,
+        |_, _, _, _| Ok::<Response, WasmAuthError>(Response::new().add_attribute("executed", "true"))
+    ).unwrap();
+
+    // every message has been executed
+    assert_eq!(res.attributes.iter().filter(|a| a.key == "executed").count(), 3);
+
+    let key = attr(&res, "session_key");
+    let session = query_session(mocks.as_ref(), &env, &key).session;
+    match session.actions {
+        AllowedActions::Include(actions) => assert_eq!(actions.len(), 2),
+        _ => panic!("expected a list of actions"),
+    }
+    run(mocks.as_mut(), &env, &bob, with_key(&key, ActionMsg::Freeze {})).unwrap();
+    run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).unwrap();
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, ActionMsg::Purge {})).is_err());
+}
+
+
+
+#[test]
+fn creation_modes_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+
+    let freeze = Action::new(&ActionMsg::Freeze {}, ActionDerivation::default()).unwrap();
+    let purge = Action::new(&ActionMsg::Purge {}, ActionDerivation::default()).unwrap();
+
+    let create = |action: &Action, mode: CreationMode, label: Option<&str>| SessionActionMsg::CreateSession(CreateSession {
+        allowed_actions: AllowedActions::Include(vec![action.clone()]),
+        session_info: session_info(),
+        policy: None,
+        mode: Some(mode),
+        label: label.map(|l| l.to_string()),
+    });
+
+    let res = run(mocks.as_mut(), &env, &admin, create(&freeze, CreationMode::Fail, None)).unwrap();
+    let key = attr(&res, "session_key");
+
+    // the existing session is kept
+    let err = run(mocks.as_mut(), &env, &admin, create(&freeze, CreationMode::Fail, None)).unwrap_err();
+    assert_eq!(err, SessionCreationError::AlreadyExists(key.clone()).into());
+
+    // sessions without a label with other actions are distinct
+    let res = run(mocks.as_mut(), &env, &admin, create(&purge, CreationMode::Replace, None)).unwrap();
+    let purge_key = attr(&res, "session_key");
+    assert_ne!(purge_key, key);
+    run(mocks.as_mut(), &env, &bob, with_key(&key, ActionMsg::Freeze {})).unwrap();
+    run(mocks.as_mut(), &env, &bob, with_key(&purge_key, ActionMsg::Purge {})).unwrap();
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, ActionMsg::Purge {})).is_err());
+
+    // the sessions under the same label conflict whatever their actions are
+    let res = run(mocks.as_mut(), &env, &admin, create(&freeze, CreationMode::Fail, Some("game"))).unwrap();
+    let labeled = attr(&res, "session_key");
+    assert!(labeled != key && labeled != purge_key);
+    let err = run(mocks.as_mut(), &env, &admin, create(&purge, CreationMode::Fail, Some("game"))).unwrap_err();
+    assert_eq!(err, SessionCreationError::AlreadyExists(labeled.clone()).into());
+
+    // the actions of both sessions are allowed after merging
+    let res = run(mocks.as_mut(), &env, &admin, create(&purge, CreationMode::Merge, Some("game"))).unwrap();
+    assert_eq!(attr(&res, "session_key"), labeled);
+    assert_eq!(attr(&res, "nonce"), "1");
+    run(mocks.as_mut(), &env, &bob, with_key(&labeled, ActionMsg::Freeze {})).unwrap();
+    run(mocks.as_mut(), &env, &bob, with_key(&labeled, ActionMsg::Purge {})).unwrap();
+
+    // only the new actions are allowed after replacing
+    let res = run(mocks.as_mut(), &env, &admin, create(&purge, CreationMode::Replace, Some("game"))).unwrap();
+    assert_eq!(attr(&res, "session_key"), labeled);
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&labeled, ActionMsg::Freeze {})).is_err());
+    run(mocks.as_mut(), &env, &bob, with_key(&labeled, ActionMsg::Purge {})).unwrap();
+    // while the sessions without the label are untouched
+    run(mocks.as_mut(), &env, &bob, with_key(&key, ActionMsg::Freeze {})).unwrap();
+
+    // the label is free again once the session is revoked
+    let revoke = SessionActionMsg::RevokeSession(RevokeKeyMsg { session_key: labeled.clone() });
+    run(mocks.as_mut(), &env, &admin, revoke).unwrap();
+    let res = run(mocks.as_mut(), &env, &admin, create(&purge, CreationMode::Fail, Some("game"))).unwrap();
+    assert_ne!(attr(&res, "session_key"), labeled);
+
+    let err = run(mocks.as_mut(), &env, &admin, create(&freeze, CreationMode::Fail, Some("a/b"))).unwrap_err();
+    assert_eq!(err, SessionCreationError::InvalidLabel("a/b".to_string()).into());
+}
+
+
+
+#[test]
+fn session_events_work() {
+    let mut mocks = get_account_deps();
+    let mut env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+
+    // the events as they are returned by the chain
+    let chain_events = |res: &Response| res.events
+        .iter()
+        .map(|e| Event::new(format!("wasm-{}", e.ty)).add_attributes(e.attributes.clone()))
+        .collect::<Vec<Event>>();
+
+    let expiring = expiring_info(BOB_ADDR, Expiration::AtHeight(env.block.height + 10));
+    let res = run(mocks.as_mut(), &env, &admin, create_msg_with(expiring, None)).unwrap();
+    let key = attr(&res, "session_key");
+
+    match parse_session_events(&chain_events(&res)).unwrap().as_slice() {
+        [SessionEvent::Created(created)] => {
+            assert_eq!(created.session_key, key);
+            assert_eq!(created.grantee, BOB_ADDR);
+            assert_eq!(created.nonce, 0);
+            assert!(created.parent_key.is_none());
+        },
+        other => panic!("unexpected events: {:?}", other),
+    }
+
+    let res = run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).unwrap();
+    match parse_session_events(&res.events).unwrap().as_slice() {
+        [SessionEvent::Used(used)] => assert_eq!(used.session_key, key),
+        other => panic!("unexpected events: {:?}", other),
+    }
+
+    env.block.height += 10;
+    let res = run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).unwrap();
+    match parse_session_events(&chain_events(&res)).unwrap().as_slice() {
+        [SessionEvent::Expired(expired)] => assert_eq!(expired.session_key, key),
+        other => panic!("unexpected events: {:?}", other),
+    }
+
+    let res = run(mocks.as_mut(), &env, &admin, create_msg(None)).unwrap();
+    let key = attr(&res, "session_key");
+    let res = run(mocks.as_mut(), &env, &admin, SessionActionMsg::RevokeSession(RevokeKeyMsg { 
+        session_key: key.clone() 
+    })).unwrap();
+    
+    let events = parse_session_events(&res.events).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].session_key(), key);
+    assert!(matches!(events[0], SessionEvent::Revoked(_)));
+
+    // unrelated events are skipped and malformed ones are rejected
+    let other = Event::new("transfer").add_attribute("session_key", "key");
+    assert!(parse_session_events(&[other]).unwrap().is_empty());
+    assert!(parse_session_events(&[Event::new("wasm-session_used")]).is_err());
+}
+
+
+
+#[test]
+fn expired_key_policy_works() {
+    let mut mocks = get_account_deps();
+    let mut env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+
+    save_session_config(mocks.as_mut().storage, &SessionConfig { 
+        on_expired: Some(ExpiredKeyPolicy::Fail), 
+        ..Default::default() 
+    }).unwrap();
+
+    let expiring = expiring_info(BOB_ADDR, Expiration::AtHeight(env.block.height + 10));
+    let res = run(mocks.as_mut(), &env, &admin, create_msg_with(expiring, None)).unwrap();
+    let key = attr(&res, "session_key");
+
+    env.block.height += 10;
+    assert!(run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).is_err());
+    assert!(SESSIONS.has(mocks.as_ref().storage, key.clone()));
+
+    // only the account can clean it up
+    let revoke = SessionActionMsg::RevokeSession(RevokeKeyMsg { session_key: key.clone() });
+    assert_eq!(
+        run(mocks.as_mut(), &env, &bob, revoke.clone()).unwrap_err(), 
+        WasmAuthError::from(SessionError::NotOwner)
+    );
+    run(mocks.as_mut(), &env, &admin, revoke).unwrap();
+    assert!(!SESSIONS.has(mocks.as_ref().storage, key));
+
+    // revoked silently by default
+    save_session_config(mocks.as_mut().storage, &SessionConfig::default()).unwrap();
+    let expiring = expiring_info(BOB_ADDR, Expiration::AtHeight(env.block.height + 10));
+    let res = run(mocks.as_mut(), &env, &admin, create_msg_with(expiring, None)).unwrap();
+    let key = attr(&res, "session_key");
+
+    env.block.height += 10;
+    let res = run(mocks.as_mut(), &env, &bob, with_key(&key, send_msg(1, "ustars"))).unwrap();
+    assert_eq!(attr(&res, "status"), "revoked");
+    assert!(!SESSIONS.has(mocks.as_ref().storage, key));
+}
+
+
+
+#[test]
+fn session_admins_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let alice = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+    let eve = person_info(EVE_ADDR);
+
+    // without admins only the native credentials and the contract itself manage the sessions
+    let not_owner = WasmAuthError::from(SessionError::NotOwner);
+    assert_eq!(run(mocks.as_mut(), &env, &eve, create_msg(None)).unwrap_err(), not_owner);
+    let res = run(mocks.as_mut(), &env, &alice, create_msg(None)).unwrap();
+    let key = attr(&res, "session_key");
+    let revoke = SessionActionMsg::RevokeSession(RevokeKeyMsg { session_key: key.clone() });
+    assert_eq!(run(mocks.as_mut(), &env, &eve, revoke.clone()).unwrap_err(), not_owner);
+    let revoke_all = SessionActionMsg::RevokeSessions(RevokeSessionsMsg::All {});
+    assert_eq!(run(mocks.as_mut(), &env, &eve, revoke_all).unwrap_err(), not_owner);
+    assert!(SESSIONS.has(mocks.as_ref().storage, key.clone()));
+    let contract = person_info(env.contract.address.as_str());
+    run(mocks.as_mut(), &env, &contract, revoke).unwrap();
+    assert!(!SESSIONS.has(mocks.as_ref().storage, key));
+
+    save_session_config(mocks.as_mut().storage, &SessionConfig { 
+        admins: Some(SessionAdmins { 
+            addresses: vec![ALICE_ADDR.to_string(), EVE_ADDR.to_string()], 
+            ..Default::default() 
+        }),
+        ..Default::default() 
+    }).unwrap();
+
+    // only the admins can create sessions
+    assert!(run(mocks.as_mut(), &env, &bob, create_msg(None)).is_err());
+    let res = run(mocks.as_mut(), &env, &alice, create_msg(None)).unwrap();
+    let key = attr(&res, "session_key");
+
+    let session = query_session(mocks.as_ref(), &env, &key).session;
+    assert_eq!(session.granter, env.contract.address.to_string());
+
+    let revoke = SessionActionMsg::RevokeSession(RevokeKeyMsg { session_key: key.clone() });
+    assert!(run(mocks.as_mut(), &env, &bob, revoke.clone()).is_err());
+
+    // any admin can revoke the sessions granted by the account
+    run(mocks.as_mut(), &env, &eve, revoke).unwrap();
+    assert!(!SESSIONS.has(mocks.as_ref().storage, key));
+
+    // including the ones granted by the other admins
+    let res = run_as(mocks.as_mut(), &env, &alice, create_msg(None), Some(ALICE_ADDR.to_string())).unwrap();
+    let key = attr(&res, "session_key");
+    let res = run(mocks.as_mut(), &env, &eve, SessionActionMsg::RevokeSessions(RevokeSessionsMsg::All {})).unwrap();
+    assert_eq!(attr(&res, "revoked"), "1");
+    assert!(!SESSIONS.has(mocks.as_ref().storage, key));
+
+    // extra addresses aren't required to be credentials of the account
+    assert!(!CREDENTIAL_INFOS.has(mocks.as_ref().storage, EVE_ADDR.to_string()));
+    run(mocks.as_mut(), &env, &eve, create_msg(None)).unwrap();
+
+    // signed actions are authorized by the credentials of the account whoever sends them
+    let eth = test_signers().eth;
+    let cred = eth.credential(Binary::from(b"account")).unwrap();
+    let info = cred.verify(mocks.as_ref()).unwrap();
+    CREDENTIAL_INFOS.save(mocks.as_mut().storage, cred.id(), &info).unwrap();
+    PRIMARY_ID.save(mocks.as_mut().storage, &cred.id()).unwrap();
+
+    let msg = SessionActionMsg::<ActionMsg>::RevokeSessions(RevokeSessionsMsg::All {});
+    let signed = |nonce| signed_data_msg(&eth, &AccountContext::from_env(&env, nonce), vec![msg.to_string()]).unwrap();
+    let action = |signed| SessionAction { msg: msg.clone(), signed: Some(signed) };
+    let handle = |deps: DepsMut, signed: SignedDataMsg| handle_session_action(
+        deps, &env, &bob, action(signed), None,
+        |_, _, _, _| Ok::<Response, WasmAuthError>(Response::new())
+    );
+    let res = handle(mocks.as_mut(), signed(0)).unwrap();
+    assert_eq!(attr(&res, "revoked"), "1");
+    assert_eq!(ACCOUNT_NUMBER.load(mocks.as_ref().storage).unwrap(), 1);
+    // can't be replayed
+    assert!(handle(mocks.as_mut(), signed(0)).is_err());
+}
+
+
+
+#[test]
+fn adr036_envelopes_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let signers = test_signers();
+    let cosmos = &signers.cosmos;
+
+    let data = Binary::from("envelope".as_bytes());
+    let wrapped = wrap_adr036(&data, BOB_ADDR).unwrap();
+    assert_eq!(unwrap_adr036(&wrapped), Some((data.clone(), BOB_ADDR.to_string())));
+
+    // sign docs with fees, chain or sequence aren't ADR-036
+    let mut doc = StdSignDoc::new(&data, BOB_ADDR);
+    doc.chain_id = "elgafar-1".to_string();
+    assert!(unwrap_adr036(&to_json_binary(&doc).unwrap()).is_none());
+    assert!(unwrap_adr036(&data).is_none());
+
+    let key = signed_session(mocks.as_mut(), &env, cosmos);
+    let envelope = |nonce: u64| to_json_binary(&MsgDataToSign {
+        chain_id: env.block.chain_id.clone(),
+        contract_address: env.contract.address.to_string(),
+        messages: vec![to_json_string(&send_msg(1, "ustars")).unwrap()],
+        nonce: nonce.into(),
+    }).unwrap();
+
+    // the data is the sign doc that `signArbitrary` of the wallet has signed
+    let wrapped_signed = |envelope: &Binary, signer: &str| SignedDataMsg {
+        data: wrap_adr036(envelope, signer).unwrap(),
+        signature: cosmos.sign(envelope).unwrap(),
+        payload: cosmos.payload(),
+    };
+    let mut verify = |signed: SignedDataMsg| handle_session_action(
+        mocks.as_mut(), &env, &admin,
+        SessionAction { msg: with_key(&key, send_msg(1, "ustars")), signed: Some(signed) },
+        None,
+        |_, _, _, _| Ok::<Response, WasmAuthError>(Response::new())
+    );
+
+    // the sign doc must be signed by the grantee
+    let err = verify(wrapped_signed(&envelope(0), EVE_ADDR)).unwrap_err();
+    assert_eq!(err, EnvelopeError::Adr036Signer(EVE_ADDR.to_string()).into());
+
+    let signed = wrapped_signed(&envelope(0), &cosmos.address());
+    let res = verify(signed.clone()).unwrap();
+    assert_eq!(attr(&res, "status"), "success");
+    assert_eq!(attr(&res, "nonce"), "1");
+
+    // can't be replayed
+    let err = verify(signed).unwrap_err();
+    assert_eq!(err, EnvelopeError::Nonce(1).into());
+
+    // the signature covers the envelope inside of the sign doc
+    let mut tampered = wrapped_signed(&envelope(1), &cosmos.address());
+    tampered.signature = cosmos.sign(&envelope(2)).unwrap();
+    assert!(matches!(verify(tampered).unwrap_err(), WasmAuthError::Auth(_)));
+
+    let res = verify(wrapped_signed(&envelope(1), &cosmos.address())).unwrap();
+    assert_eq!(attr(&res, "nonce"), "2");
+}
+
+
+#[test]
+fn signed_grantees_are_verified_once() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let signers = test_signers();
+    let api = CountingApi::new();
+    let msg = send_msg(1, "ustars");
+    let messages = vec![to_json_string(&msg).unwrap()];
+
+    let passkey_ext = Some(InfoExtension::Passkey(signers.passkey.info()));
+
+    // gas of the single signature check of every grantee
+    let grantees : [(&dyn DataSigner, Option<InfoExtension>, u64); 4] = [
+        (&signers.eth, None, SECP256K1_RECOVER_GAS),
+        (&signers.cosmos, None, SECP256K1_VERIFY_GAS),
+        (&signers.ed25519, None, ED25519_VERIFY_GAS),
+        (&signers.passkey, passkey_ext, SECP256R1_VERIFY_GAS),
+    ];
+
+    for (signer, extension, gas) in grantees {
+        let cred = signer.credential(Binary::from(b"grantee")).unwrap();
+        let info = CredentialInfo { extension, ..cred.verify(mocks.as_ref()).unwrap() };
+        let session_info = SessionInfo { expiration: None, granter: None, grantee: (cred.id(), info) };
+        let res = run(mocks.as_mut(), &env, &admin, create_msg_with(session_info, None)).unwrap();
+        let key = attr(&res, "session_key");
+
+        let envelope = AccountContext::from_env(&env, 0).message(messages.clone()).unwrap();
+        let signed = signer.signed_data(envelope).unwrap();
+
+        let mut call = |signed: SignedDataMsg| handle_session_action(
+            DepsMut { storage: &mut mocks.storage, api: &api, querier: QuerierWrapper::new(&mocks.querier) }, 
+            &env, &admin,
+            SessionAction { msg: with_key(&key, msg.clone()), signed: Some(signed) },
+            None,
+            |_, _, _, _| Ok::<Response, WasmAuthError>(Response::new())
+        );
+        // every signature is really checked including the ones of passkeys
+        let mut tampered = signed.clone();
+        let mut signature = tampered.signature.to_vec();
+        signature[10] ^= 1;
+        tampered.signature = signature.into();
+        assert!(call(tampered).is_err());
+        api.take();
+
+        let res = call(signed.clone()).unwrap();
+        assert_eq!(attr(&res, "nonce"), "1");
+        assert_eq!(api.take(), (1, gas));
+
+        // the nonce has moved on
+        assert!(call(signed).is_err());
+        api.take();
+    }
+
+    // native grantees are only checked against the sender
+    let res = run(mocks.as_mut(), &env, &admin, create_msg(None)).unwrap();
+    let key = attr(&res, "session_key");
+    let deps = DepsMut { storage: &mut mocks.storage, api: &api, querier: QuerierWrapper::new(&mocks.querier) };
+    run(deps, &env, &person_info(BOB_ADDR), with_key(&key, msg)).unwrap();
+    assert_eq!(api.take(), (0, 0));
+}
+
+
+
+#[test]
+fn passkey_sign_counters_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let passkey = test_signers().passkey;
+    let msg = send_msg(1, "ustars");
+    let messages = vec![to_json_string(&msg).unwrap()];
+
+    // registered while the counter of the authenticator was zero
+    let cred = passkey.credential(Binary::from(b"grantee")).unwrap();
+    let info = CredentialInfo { 
+        extension: Some(InfoExtension::Passkey(passkey.info())), 
+        ..cred.verify(mocks.as_ref()).unwrap() 
+    };
+    let session_info = SessionInfo { expiration: None, granter: None, grantee: (cred.id(), info) };
+    let res = run(mocks.as_mut(), &env, &admin, create_msg_with(session_info, None)).unwrap();
+    let key = attr(&res, "session_key");
+
+    let mut call = |signer: &PasskeySigner, nonce: u64, payload: bool| {
+        let envelope = AccountContext::from_env(&env, nonce).message(messages.clone()).unwrap();
+        let mut signed = signer.signed_data(envelope).unwrap();
+        if !payload {
+            signed.payload = None;
+        }
+        handle_session_action(
+            mocks.as_mut(), &env, &admin,
+            SessionAction { msg: with_key(&key, msg.clone()), signed: Some(signed) },
+            None,
+            |_, _, _, _| Ok::<Response, WasmAuthError>(Response::new())
+        )
+    };
+
+    // every assertion reports a higher counter
+    let res = call(&passkey.clone().with_counter(1), 0, true).unwrap();
+    assert_eq!(attr(&res, "nonce"), "1");
+    let res = call(&passkey.clone().with_counter(7), 1, true).unwrap();
+    assert_eq!(attr(&res, "nonce"), "2");
+
+    // without the payload the stored authenticator data doesn't match the signed one
+    assert!(call(&passkey.clone().with_counter(8), 2, false).is_err());
+
+    // authenticator data of another relying party
+    let seed = [SIGNERS_SEED, b"passkey".as_slice()].concat();
+    let other_rp = PasskeySigner::from_seed(&seed, "https://example.com").unwrap();
+    assert!(call(&other_rp, 2, true).is_err());
+}
+
+
+
+#[test]
+fn canonical_envelopes_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let signers = test_signers();
+    let key = signed_session(mocks.as_mut(), &env, &signers.eth);
+
+    let mut verify = |data: String| handle_session_action(
+        mocks.as_mut(), &env, &admin,
+        SessionAction { 
+            msg: with_key(&key, send_msg(1, "ustars")), 
+            signed: Some(sign_raw(&signers.eth, &data)) 
+        },
+        None,
+        |_, _, _, _| Ok::<Response, WasmAuthError>(Response::new())
+    );
+
+    let envelope = |nonce: &str, chain_id: &str, amount: &str| format!(
+        r#"{{ "nonce": {}, "messages": ["{{\"execute\": {{\"msgs\": [{{\"bank\": {{\"send\": {{\"amount\": [{{\"amount\": \"{}\", \"denom\": \"ustars\"}}], \"to_address\": \"{}\"}}}}}}]}}}}"],
+            "contract_address": "{}", "chain_id": "{}" }}"#,
+        nonce, amount, EVE_ADDR, env.contract.address, chain_id
+    );
+
+    // another key order, whitespace and a numeric nonce
+    let res = verify(envelope("0", &env.block.chain_id, "1")).unwrap();
+    assert_eq!(attr(&res, "nonce"), "1");
+
+    // numbers with fractions or exponents
+    let res = verify(envelope("1.0", &env.block.chain_id, "1")).unwrap();
+    assert_eq!(attr(&res, "nonce"), "2");
+    let res = verify(envelope("0.2e1", &env.block.chain_id, "1")).unwrap();
+    assert_eq!(attr(&res, "nonce"), "3");
+
+    let err = verify(envelope("\"3\"", "other-1", "1")).unwrap_err();
+    assert_eq!(err, EnvelopeError::ChainId.into());
+
+    let err = verify(envelope("3.5", &env.block.chain_id, "1")).unwrap_err();
+    assert_eq!(err, EnvelopeError::Nonce(3).into());
+
+    let err = verify(envelope("3", &env.block.chain_id, "2")).unwrap_err();
+    assert_eq!(err, EnvelopeError::Message(0).into());
+
+    let err = verify(envelope("3e100", &env.block.chain_id, "1")).unwrap_err();
+    assert!(matches!(err, WasmAuthError::Envelope(EnvelopeError::Invalid(_))));
+
+    // a valid envelope still needs the signature of the grantee
+    let other = TestSigners::new(b"other", "stars").unwrap();
+    let err = handle_session_action(
+        mocks.as_mut(), &env, &admin,
+        SessionAction { 
+            msg: with_key(&key, send_msg(1, "ustars")), 
+            signed: Some(sign_raw(&other.eth, &envelope("3", &env.block.chain_id, "1"))) 
+        },
+        None,
+        |_, _, _, _| Ok::<Response, WasmAuthError>(Response::new())
+    ).unwrap_err();
+    assert!(matches!(err, WasmAuthError::Auth(_)));
+}
+
+
+#[test]
+fn envelope_validity_bounds_work() {
+    let mut mocks = get_account_deps();
+    let env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let signers = test_signers();
+    let key = signed_session(mocks.as_mut(), &env, &signers.eth);
+
+    let envelope = |nonce: u64, bounds: String| to_json_string(&MsgDataToSign {
+        chain_id: env.block.chain_id.clone(),
+        contract_address: env.contract.address.to_string(),
+        messages: vec![to_json_string(&send_msg(1, "ustars")).unwrap()],
+        nonce: nonce.into(),
+    }).unwrap().replacen('{', &format!("{{{},", bounds), 1);
+
+    let mut verify = |signed: SignedDataMsg| handle_session_action(
+        mocks.as_mut(), &env, &admin,
+        SessionAction { msg: with_key(&key, send_msg(1, "ustars")), signed: Some(signed) },
+        None,
+        |_, _, _, _| Ok::<Response, WasmAuthError>(Response::new())
+    );
+
+    let height = env.block.height;
+    let stale = envelope(0, format!(r#""valid_until":{{"height":{}}}"#, height - 1));
+    let err = verify(sign_raw(&signers.eth, &stale)).unwrap_err();
+    assert_eq!(err, ValidityError::Stale.into());
+
+    let early = envelope(0, format!(r#""valid_after":{{"time":"{}"}}"#, env.block.time.plus_seconds(60).nanos()));
+    let err = verify(sign_raw(&signers.eth, &early)).unwrap_err();
+    assert_eq!(err, ValidityError::NotYetValid.into());
+
+    // both bounds are inclusive
+    let bounded = envelope(0, format!(r#""valid_after":{{"height":{}}},"valid_until":{{"height":{}}}"#, height, height));
+
+    // signed by someone else
+    let other = TestSigners::new(b"other", "stars").unwrap();
+    let err = verify(SignedDataMsg { payload: None, ..sign_raw(&other.eth, &bounded) }).unwrap_err();
+    assert!(matches!(err, WasmAuthError::Auth(_)));
+
+    let res = verify(sign_raw(&signers.eth, &bounded)).unwrap();
+    assert_eq!(attr(&res, "nonce"), "1");
+    assert!(verify(sign_raw(&signers.eth, &bounded)).is_err());
+
+    // the credentials of the account check the same bounds
+    let cred = signers.eth.credential(Binary::from(b"account")).unwrap();
+    let info = cred.verify(mocks.as_ref()).unwrap();
+    CREDENTIAL_INFOS.save(mocks.as_mut().storage, cred.id(), &info).unwrap();
+    PRIMARY_ID.save(mocks.as_mut().storage, &cred.id()).unwrap();
+
+    let messages = vec![ActionMsg::Freeze {}];
+    let signed_msgs = |bounds: String| to_json_string(&MsgDataToSign {
+        chain_id: env.block.chain_id.clone(),
+        contract_address: env.contract.address.to_string(),
+        messages: vec![ActionMsg::Freeze {}.to_string()],
+        nonce: 0u64.into(),
+    }).unwrap().replacen('{', &format!("{{{},", bounds), 1);
+
+    let stale = signed_msgs(format!(r#""valid_until":{{"height":{}}}"#, height - 1));
+    let err = verify_signed(mocks.as_ref(), &env, messages.clone(), sign_raw(&signers.eth, &stale)).unwrap_err();
+    assert_eq!(err, ValidityError::Stale.into());
+
+    let bounded = signed_msgs(format!(r#""valid_until":{{"height":{}}}"#, height));
+    verify_signed(mocks.as_ref(), &env, messages, sign_raw(&signers.eth, &bounded)).unwrap();
+}
+
+
+#[test]
+fn export_and_restore_work() {
+    let mut mocks = get_account_deps();
+    let mut env = get_mock_env();
+    let admin = person_info(ALICE_ADDR);
+    let bob = person_info(BOB_ADDR);
+    let height = env.block.height;
+
+    let eth = test_signers().eth;
+    let eth_cred = eth.credential(Binary::from(b"account")).unwrap();
+    let info = eth_cred.verify(mocks.as_ref()).unwrap();
+    CREDENTIAL_INFOS.save(mocks.as_mut().storage, eth_cred.id(), &info).unwrap();
+    PRIMARY_ID.save(mocks.as_mut().storage, &eth_cred.id()).unwrap();
+    ACCOUNT_NUMBER.save(mocks.as_mut().storage, &5).unwrap();
+    save_session_config(mocks.as_mut().storage, &SessionConfig { auto_prune: Some(3), ..Default::default() }).unwrap();
+
+    let parent_info = expiring_info(BOB_ADDR, Expiration::AtHeight(height + 100));
+    let res = run(mocks.as_mut(), &env, &admin, create_msg_with(parent_info, None)).unwrap();
+    let parent_key = attr(&res, "session_key");
+    let res = run(mocks.as_mut(), &env, &bob, SessionActionMsg::CreateSubSession(CreateSubSession {
+        parent_key: parent_key.clone(),
+        allowed_actions: AllowedActions::Include(vec![
+            Action::new(&send_msg(0, "ustars"), ActionDerivation::default()).unwrap()
+        ]),
+        session_info: expiring_info(EVE_ADDR, Expiration::AtHeight(height + 50)),
+        policy: None,
+    })).unwrap();
+    let child_key = attr(&res, "session_key");
+    let short_info = expiring_info(EVE_ADDR, Expiration::AtHeight(height + 5));
+    let res = run(mocks.as_mut(), &env, &admin, create_msg_with(short_info, None)).unwrap();
+    let short_key = attr(&res, "session_key");
+    run(mocks.as_mut(), &env, &bob, with_key(&parent_key, send_msg(1, "ustars"))).unwrap();
+
+    let export = export_auth_state(mocks.as_ref().storage, &env).unwrap();
+    let old_account = env.contract.address.to_string();
+    assert_eq!(export.state.account, old_account);
+    assert_eq!(export.state.credentials.account_number, 5);
+    assert_eq!(export.state.session_parents, vec![(child_key.clone(), parent_key.clone())]);
+    // deterministic
+    assert_eq!(export, export_auth_state(mocks.as_ref().storage, &env).unwrap());
+
+    // can't be restored into the same contract
+    assert!(restore_credentials(mocks.as_mut().storage, &env, &old_account, &old_account, export.clone()).is_err());
+
+    let mut fresh = get_mock_deps();
+    env.contract.address = fresh.api.addr_make("new_account");
+    env.block.height += 10;
+
+    // envelopes signed for the new address before the restore
+    let messages = vec![MESSAGE_TEXT.to_string()];
+    let presigned = |nonce| signed_data_msg(&eth, &AccountContext::from_env(&env, nonce), messages.clone()).unwrap();
+
+    // only the old account can restore
+    let err = restore_credentials(fresh.as_mut().storage, &env, ALICE_ADDR, &old_account, export.clone()).unwrap_err();
+    assert_eq!(err, RestoreError::Unauthorized.into());
+
+    // tampered state
+    let mut tampered = export.clone();
+    tampered.state.credentials.has_natives = false;
+    let err = restore_credentials(fresh.as_mut().storage, &env, &old_account, &old_account, tampered).unwrap_err();
+    assert_eq!(err, RestoreError::HashMismatch.into());
+
+    // the account can't be swapped without the hash
+    let third = fresh.api.addr_make("third_contract").to_string();
+    let mut forged = export.clone();
+    forged.state.account = third.clone();
+    let err = restore_credentials(fresh.as_mut().storage, &env, &third, &old_account, forged.clone()).unwrap_err();
+    assert_eq!(err, RestoreError::HashMismatch.into());
+
+    // a rehashed export of a third contract isn't the one of the expected account
+    let forged = AuthStateExport::new(forged.state).unwrap();
+    let err = restore_credentials(fresh.as_mut().storage, &env, &third, &old_account, forged.clone()).unwrap_err();
+    assert_eq!(err, RestoreError::Unauthorized.into());
+    let err = restore_credentials(fresh.as_mut().storage, &env, &old_account, &old_account, forged).unwrap_err();
+    assert_eq!(err, RestoreError::Unauthorized.into());
+    assert!(!PRIMARY_ID.exists(fresh.as_ref().storage));
+
+    restore_credentials(fresh.as_mut().storage, &env, &old_account, &old_account, export.clone()).unwrap();
+    let storage = fresh.as_ref().storage;
+    assert_eq!(PRIMARY_ID.load(storage).unwrap(), eth_cred.id());
+    assert!(HAS_NATIVES.load(storage).unwrap());
+    // the counters continue from the old account
+    assert_eq!(ACCOUNT_NUMBER.load(storage).unwrap(), 5);
+    assert_eq!(
+        SESSIONS.load(storage, parent_key.clone()).unwrap().nonce, 
+        SESSIONS.load(mocks.as_ref().storage, parent_key.clone()).unwrap().nonce
+    );
+    let err = verify_signed(fresh.as_ref(), &env, messages.clone(), presigned(0)).unwrap_err();
+    assert!(matches!(err, WasmAuthError::Auth(_)));
+    verify_signed(fresh.as_ref(), &env, messages.clone(), presigned(5)).unwrap();
+    assert!(SESSIONS.has(storage, child_key.clone()));
+    assert!(!SESSIONS.has(storage, short_key));
+    assert_eq!(SESSIONS.load(storage, parent_key.clone()).unwrap().granter, env.contract.address.to_string());
+
+    // sub-sessions still belong to their parents
+    let revoke = SessionActionMsg::RevokeSession(RevokeKeyMsg { session_key: parent_key.clone() });
+    run(fresh.as_mut(), &env, &admin, revoke).unwrap();
+    assert!(!SESSIONS.has(fresh.as_ref().storage, child_key));
+
+    // only once
+    let err = restore_credentials(fresh.as_mut().storage, &env, &old_account, &old_account, export).unwrap_err();
+    assert_eq!(err, RestoreError::AlreadyInitialized.into());
+}
@@ -1,18 +1,23 @@
-use types::{errors::CredentialError, wasm::{DepsMut, Storage}};
-use saa_wasm::{credential_count, get_stored_credentials, 
-    reset_credentials, save_credentials, update_credentials
+use types::{errors::CredentialError, wasm::{
+    from_json, instantiate2_address, testing::MockApi, to_json_binary, Addr, Api, Binary, DepsMut, HexBinary, Storage
+}};
+use saa_wasm::{credential_count, credential_proof, credentials_root, get_stored_credentials, handle_credentials_query, 
+    predict_account_address, predicted_envelope, reset_credentials, save_credentials, 
+    save_credentials_via_registry, update_credentials, update_credentials_root, verify_cred_actions, verify_credential_proof, 
+    testing::{CredentialSigner, TestSigners}, CredentialProof, CredentialsQueryMsg, RegistryContext
 };
 
 use crate::utils::{
-    base_credentials, get_cosmos_arbitrary, get_eth_personal, get_mock_deps, get_mock_env, get_passkey, person_info, ALICE_ADDR, BOB_ADDR, MESSAGE_TEXT, SIGN_NONCE
+    test_signers, base_credentials, get_cosmos_arbitrary, get_eth_personal, get_mock_deps, get_mock_env, get_passkey, person_info, 
+    ALICE_ADDR, BOB_ADDR, EVE_ADDR, MESSAGE_TEXT, SIGN_CHAIN_ID, SIGN_CONTRACT_ADDRESS, SIGN_NONCE
 };
 
 use smart_account_auth::{
-    errors::StorageError, AuthError, Caller, CheckOption, Credential, CredentialData, CredentialId, CredentialInfo, CredentialName, CredentialsWrapper, ReplayParams, Verifiable
+    errors::StorageError, AuthError, Caller, CheckOption, Credential, CredentialData, CredentialId, CredentialInfo, CredentialName, CredentialsWrapper, ReplayParams, Verifiable, VerifiedData
 };
 
 use types::{
-    stores::{ACCOUNT_NUMBER, CREDENTIAL_INFOS, HAS_NATIVES, PRIMARY_ID},
+    stores::{ACCOUNT_NUMBER, CREDENTIALS_ROOT, CREDENTIAL_INFOS, HAS_NATIVES, PRIMARY_ID},
     UpdateOperation
 };
 
@@ -115,11 +120,11 @@ fn credential_crds_work() {
     // none of the two should change
     remove_credential_smart(storage, &passkey_cred.id()).unwrap();
     assert_eq!(PRIMARY_ID.load(storage).unwrap(), native.id());
-    assert_eq!(HAS_NATIVES.load(storage).unwrap(), true);
+    assert!(HAS_NATIVES.load(storage).unwrap());
 
     remove_credential_smart(storage, &native.id()).unwrap();
     assert_eq!(PRIMARY_ID.load(storage).unwrap(), eth_cred.id());
-    assert_eq!(HAS_NATIVES.load(storage).unwrap(), false);
+    assert!(!HAS_NATIVES.load(storage).unwrap());
     assert_eq!(credential_count(storage), 2);
 }
 
@@ -321,3 +326,235 @@ fn update_cred_data_remove_simple() {
 
 
 
+
+
+
+
+#[test]
+fn save_credentials_via_registry_works() {
+    let mut mocks = get_mock_deps();
+    let deps = mocks.as_mut();
+
+    // the account has its own address and lives on another chain
+    let mut env = get_mock_env();
+    env.contract.address = Addr::unchecked(EVE_ADDR);
+    env.block.chain_id = "stargaze-1".to_string();
+
+    // called by the registry
+    let registry = person_info(SIGN_CONTRACT_ADDRESS);
+    let params = ReplayParams::new(SIGN_NONCE, CheckOption::Messages(vec![MESSAGE_TEXT.to_string()]));
+    let data = CredentialData::new(base_credentials(), Some(true)).with_native(ALICE_ADDR);
+
+    // signed for the registry and not for the account
+    assert!(data.verify(deps.as_ref(), &env, &registry, params.clone()).is_err());
+
+    let context = RegistryContext::new(Addr::unchecked(SIGN_CONTRACT_ADDRESS), Addr::unchecked(ALICE_ADDR))
+        .with_chain_id(SIGN_CHAIN_ID);
+
+    let verified = save_credentials_via_registry(deps, &env, &registry, &context, &data, params).unwrap();
+    
+    // the original sender is saved as a native caller 
+    assert!(verified.has_natives);
+    assert!(CREDENTIAL_INFOS.has(mocks.as_ref().storage, ALICE_ADDR.to_string()));
+    assert_eq!(credential_count(mocks.as_ref().storage), base_credentials().len() + 1);
+}
+
+
+
+
+#[test]
+fn instantiate2_prediction_works() {
+    let mut mocks = get_mock_deps();
+    let mut env = get_mock_env();
+    let creator = mocks.api.addr_make("creator");
+    let checksum = [7u8; 32];
+    let salt = b"account-1";
+    let messages = vec![MESSAGE_TEXT.to_string()];
+
+    // signed before the account exists
+    let envelope = predicted_envelope(
+        mocks.as_ref().api, &env, &checksum, creator.as_str(), salt, messages.clone()
+    ).unwrap();
+    let cred = test_signers().eth.credential(to_json_binary(&envelope).unwrap()).unwrap();
+    let data = CredentialData::new(vec![cred.clone()], None);
+    let params = ReplayParams::new(0, CheckOption::Messages(messages));
+
+    // other salts give other addresses
+    let other = predict_account_address(mocks.as_ref().api, &checksum, creator.as_str(), b"account-2").unwrap();
+    env.contract.address = other;
+    assert!(data.verify(mocks.as_ref(), &env, &person_info(creator.as_str()), params.clone()).is_err());
+
+    // instantiated at the address computed by the chain
+    let canonical = instantiate2_address(&checksum, &mocks.api.addr_canonicalize(creator.as_str()).unwrap(), salt).unwrap();
+    env.contract.address = mocks.api.addr_humanize(&canonical).unwrap();
+    assert_eq!(env.contract.address.to_string(), envelope.contract_address);
+
+    let verified = data.verify(mocks.as_ref(), &env, &person_info(creator.as_str()), params).unwrap();
+    save_credentials(mocks.as_mut().storage, &verified).unwrap();
+
+    assert_eq!(PRIMARY_ID.load(mocks.as_ref().storage).unwrap(), cred.id());
+    assert_eq!(ACCOUNT_NUMBER.load(mocks.as_ref().storage).unwrap(), 1);
+}
+
+
+
+#[test]
+fn instantiate2_prediction_matches_test_vectors() {
+    // the first vector of the instantiate2 test data of cosmjs and cosmwasm-std
+    let api = MockApi::default().with_prefix("purple");
+    let checksum = HexBinary::from_hex("13a1fc994cc6d1c81b746ee0c0ff6f90043875e0bf1d9be6b7d779fc978dc2a5").unwrap();
+    let creator = "purple1nxvenxve42424242hwamhwamenxvenxvhxf2py";
+    let salt = HexBinary::from_hex("61").unwrap();
+
+    let predicted = predict_account_address(&api, &checksum, creator, &salt).unwrap();
+    assert_eq!(predicted.as_str(), "purple1t6r960j945lfv8mhl4mage2rg97w63xeynwrupum2s2l7em4lprs9ce5hk");
+
+    let env = get_mock_env();
+    let envelope = predicted_envelope(&api, &env, &checksum, creator, &salt, vec![]).unwrap();
+    assert_eq!(envelope.contract_address, predicted.to_string());
+    assert_eq!(envelope.nonce.u64(), 0);
+}
+
+
+
+#[test]
+fn credentials_root_works() {
+    let mut mocks = get_mock_deps();
+    let deps = mocks.as_mut();
+    let env = get_mock_env();
+    let alice = person_info(ALICE_ADDR);
+
+    let eth_cred : Credential = get_eth_personal().into();
+    let passkey_cred : Credential = get_passkey().into();
+    let alice_cred : Credential = Caller::from(alice.sender.as_str()).into();
+    let params = ReplayParams::new(SIGN_NONCE, CheckOption::Messages(vec![MESSAGE_TEXT.to_string()]));
+
+    let data = CredentialData::new(vec![passkey_cred.clone(), eth_cred.clone()], Some(true))
+        .verify(deps.as_ref(), &env, &alice, params)
+        .unwrap();
+    
+    assert!(credentials_root(deps.storage).is_none());
+    save_credentials(deps.storage, &data).unwrap();
+    let root = credentials_root(deps.storage).unwrap();
+
+    for cred in [&eth_cred, &passkey_cred, &alice_cred] {
+        let proof = credential_proof(deps.storage, &cred.id()).unwrap();
+        assert_eq!(proof.root, root);
+        assert!(proof.verify());
+        assert!(verify_credential_proof(&root, &proof.id, &proof.info, &proof.proof));
+    }
+
+    // proof of one credential doesn't work for another
+    let eth_proof = credential_proof(deps.storage, &eth_cred.id()).unwrap();
+    let alice_proof = credential_proof(deps.storage, &alice_cred.id()).unwrap();
+    assert!(!verify_credential_proof(&root, &eth_proof.id, &alice_proof.info, &eth_proof.proof));
+    assert!(!verify_credential_proof(&root, &alice_proof.id, &alice_proof.info, &eth_proof.proof));
+
+    // removal changes the root
+    let op = UpdateOperation::Remove(vec![eth_cred.id()]);
+    update_credentials(deps.storage, &op).unwrap();
+    let new_root = credentials_root(deps.storage).unwrap();
+    assert_ne!(root, new_root);
+    assert!(!verify_credential_proof(&new_root, &eth_proof.id, &eth_proof.info, &eth_proof.proof));
+    assert!(credential_proof(deps.storage, &eth_cred.id()).is_err());
+    assert!(credential_proof(deps.storage, &alice_cred.id()).unwrap().verify());
+
+    // adding it back restores the same root
+    let op = UpdateOperation::Add(VerifiedData {
+        credentials: vec![(eth_cred.id(), eth_proof.info.clone())],
+        nonce: ACCOUNT_NUMBER.load(deps.storage).unwrap(),
+        ..data
+    });
+    update_credentials(deps.storage, &op).unwrap();
+    assert_eq!(credentials_root(deps.storage).unwrap(), root);
+
+    // same through the query entry point
+    let res = handle_credentials_query(deps.storage, CredentialsQueryMsg::CredentialsRoot {}).unwrap();
+    assert_eq!(from_json::<Option<Binary>>(&res).unwrap(), Some(root.clone()));
+    let res = handle_credentials_query(
+        deps.storage, 
+        CredentialsQueryMsg::CredentialProof { id: eth_cred.id() }
+    ).unwrap();
+    let proof = from_json::<CredentialProof>(&res).unwrap();
+    assert_eq!(proof.root, root);
+    assert!(proof.verify());
+    assert!(handle_credentials_query(
+        deps.storage, 
+        CredentialsQueryMsg::CredentialProof { id: "unknown".to_string() }
+    ).is_err());
+
+    reset_credentials(deps.storage, true, true).unwrap();
+    assert!(credentials_root(deps.storage).is_none());
+    let res = handle_credentials_query(deps.storage, CredentialsQueryMsg::CredentialsRoot {}).unwrap();
+    assert_eq!(from_json::<Option<Binary>>(&res).unwrap(), None);
+}
+
+
+
+#[test]
+fn credentials_root_without_stored_root_works() {
+    let mut mocks = get_mock_deps();
+    let deps = mocks.as_mut();
+    let env = get_mock_env();
+    let alice = person_info(ALICE_ADDR);
+    let params = ReplayParams::new(SIGN_NONCE, CheckOption::Messages(vec![MESSAGE_TEXT.to_string()]));
+
+    let eth_cred : Credential = get_eth_personal().into();
+    let data = CredentialData::new(vec![eth_cred.clone()], Some(true))
+        .verify(deps.as_ref(), &env, &alice, params)
+        .unwrap();
+    save_credentials(deps.storage, &data).unwrap();
+    let root = credentials_root(deps.storage).unwrap();
+
+    // saved before the root was stored
+    CREDENTIALS_ROOT.remove(deps.storage);
+    assert_eq!(credentials_root(deps.storage), Some(root.clone()));
+    let res = handle_credentials_query(deps.storage, CredentialsQueryMsg::CredentialsRoot {}).unwrap();
+    assert_eq!(from_json::<Option<Binary>>(&res).unwrap(), Some(root.clone()));
+    let proof = credential_proof(deps.storage, &eth_cred.id()).unwrap();
+    assert_eq!(proof.root, root);
+    assert!(proof.verify());
+
+    // stored on migration
+    update_credentials_root(deps.storage).unwrap();
+    assert_eq!(CREDENTIALS_ROOT.load(deps.storage).unwrap(), root);
+}
+
+
+
+#[test]
+fn test_signers_work() {
+    let mut mocks = get_mock_deps();
+    let mut env = get_mock_env();
+    env.block.chain_id = "pion-1".to_string();
+    env.contract.address = mocks.api.addr_make("account");
+
+    let signers = TestSigners::new(b"downstream", "stars").unwrap();
+    let messages = vec![MESSAGE_TEXT.to_string()];
+    let params = ReplayParams::new(0, CheckOption::Messages(messages.clone()));
+
+    let data = signers.credential_data(&env, 0, messages.clone(), None).unwrap();
+    let verified = data.verify(mocks.as_ref(), &env, &person_info(ALICE_ADDR), params).unwrap();
+    save_credentials(mocks.as_mut().storage, &verified).unwrap();
+    assert_eq!(credential_count(mocks.as_ref().storage), signers.all().len());
+
+    // every key signs for any later account number
+    for nonce in 1..=4u64 {
+        let cred = signers
+            .credentials(&env, nonce, messages.clone())
+            .unwrap()
+            .remove(nonce as usize - 1);
+        verify_cred_actions(&mocks.api, &mut mocks.storage, &env, cred, Some(messages.clone())).unwrap();
+    }
+    assert_eq!(ACCOUNT_NUMBER.load(mocks.as_ref().storage).unwrap(), 5);
+
+    // envelopes of the past account numbers are rejected
+    let stale = signers.credentials(&env, 4, messages.clone()).unwrap().remove(0);
+    assert!(verify_cred_actions(&mocks.api, &mut mocks.storage, &env, stale, Some(messages)).is_err());
+
+    // same seed, same keys
+    let again = TestSigners::new(b"downstream", "stars").unwrap();
+    assert_eq!(again.eth.address(), signers.eth.address());
+    assert_eq!(again.cosmos.address(), signers.cosmos.address());
+    assert_ne!(TestSigners::new(b"other", "stars").unwrap().eth.address(), signers.eth.address());
+}
@@ -33,6 +33,7 @@ pub enum ActionMsg {
 
 #[session_action(ActionMsg)]
 #[saa_derivable]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     Execute { 
         msgs: Vec<CosmosMsg> 
@@ -43,6 +44,7 @@ pub enum ExecuteMsg {
 
 #[session_query(ExecuteMsg)]
 #[saa_derivable]
+#[allow(clippy::large_enum_variant)]
 pub enum QueryMsg {
 
     #[returns(Vec<Coin>)]
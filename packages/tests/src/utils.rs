@@ -1,25 +1,23 @@
-#![allow(dead_code)]
 
-use saa_wasm::{SessionAction, SessionActionMsg, SessionQueryMsg, WithSessionMsg};
+use saa_wasm::testing::{sign_credential, AccountContext, CredentialSigner, TestSigners};
 use smart_account_auth::{
-    types::ClientData, utils::passkey::base64_to_url, 
-    CosmosArbitrary, Credential, CredentialAddress, CredentialData, 
-    CredentialInfo, CredentialName, EthPersonalSign, PasskeyCredential, 
-    SessionInfo
+    Caller, CosmosArbitrary, Credential, 
+    CredentialInfo, EthPersonalSign, PasskeyCredential, 
+    SessionInfo, Verifiable
 };
+use std::cell::Cell;
+use types::stores::{CREDENTIAL_INFOS, HAS_NATIVES};
 use types::wasm::{
     testing::{
         message_info, mock_dependencies, mock_env,
         MockApi, MockQuerier, MockStorage
     }, 
-    Addr, Binary, Empty, Env, MessageInfo, OwnedDeps
+    Addr, Api, CanonicalAddr, Empty, Env, MessageInfo, OwnedDeps, 
+    RecoverPubkeyError, StdResult, VerificationError
 };
 
-use crate::types::{ActionMsg, ExecuteMsg, QueryMsg};
-
 pub const SIGN_CHAIN_ID : &str = "elgafar-1";
 pub const SIGN_CONTRACT_ADDRESS : &str = "stars1wgesz5jrx3uvt29a9awkafy4p06rutxv2xdnqperde4tmzx4n2yq95mumn";
-pub const SIGN_MESSAGE_BASE64 : &str = "eyJjaGFpbl9pZCI6ImVsZ2FmYXItMSIsImNvbnRyYWN0X2FkZHJlc3MiOiJzdGFyczF3Z2VzejVqcngzdXZ0MjlhOWF3a2FmeTRwMDZydXR4djJ4ZG5xcGVyZGU0dG16eDRuMnlxOTVtdW1uIiwibWVzc2FnZXMiOlsiVGVzdGluZyBzbWFydC1hY2NvdW50LWF1dGggbGlicmFyeSJdLCJub25jZSI6IjAifQ==";
 pub const SIGN_NONCE : u64 = 0;
 pub const MESSAGE_TEXT : &str = "Testing smart-account-auth library";
 
@@ -28,34 +26,43 @@ pub const BOB_ADDR : &str = "stars1sxmr0k8u6trd5c6eu6trzyapzux7090ykujmsng7pdx0m
 pub const EVE_ADDR : &str = "stars1s5nz4hm52x9mkux8ew2v6c2emytxnedgrm03al4a2sl2m0dflg4sfppadm";
 
 
-pub fn get_eth_personal() -> EthPersonalSign {
-    EthPersonalSign {
-        signer : "0xac03048da6065e584d52007e22c69174cdf2b91a".to_string(),
-        signature: Binary::from_base64("ohEtmdTyusVQbidIhRxi7SPR8grzz99OW98gcqU2ziQ2WYL+UOVbQC8l1XPHWjg2axMAMep0ual3kl2zlnawcxs=").unwrap(),
-        message: Binary::from_base64(SIGN_MESSAGE_BASE64).unwrap()
+/// Key pairs of the base credentials
+/// Seed of the keys of `test_signers`
+pub const SIGNERS_SEED : &[u8] = b"saa-wasm-tests";
 
+
+pub fn test_signers() -> TestSigners {
+    TestSigners::new(SIGNERS_SEED, "stars").unwrap()
+}
+
+
+/// Credential of the signer for the default envelope with `MESSAGE_TEXT`
+fn signed_credential(signer: &dyn CredentialSigner) -> Credential {
+    let ctx = AccountContext::new(SIGN_CHAIN_ID, SIGN_CONTRACT_ADDRESS, SIGN_NONCE);
+    sign_credential(signer, &ctx, vec![MESSAGE_TEXT.to_string()]).unwrap()
+}
+
+
+pub fn get_eth_personal() -> EthPersonalSign {
+    match signed_credential(&test_signers().eth) {
+        Credential::EthPersonalSign(eth) => eth,
+        _ => unreachable!(),
     }
 }
 
     
 pub fn get_cosmos_arbitrary() -> CosmosArbitrary {
-    CosmosArbitrary {
-        pubkey: Binary::from_base64("A2LjUH7Q0gi7+Wi0/MnXMZqN8slsz7iHMfTWp8xUXspH").unwrap(),
-        signature: Binary::from_base64("jfoaUrJHF17xrapXWfu2KPDd2jcDI/02Rbv9PI1PWx5ugxHGVv99V1Scu7FZVKYVqrL9tCt4sX3hFX/7ul4dcg==").unwrap(),
-        message: Binary::from_base64(SIGN_MESSAGE_BASE64).unwrap(),
-        address: String::from("stars1v85m4sxnndwmswtd8jrz3cd2m8u8eegqdxyluz"),
+    match signed_credential(&test_signers().cosmos) {
+        Credential::CosmosArbitrary(cosmos) => cosmos,
+        _ => unreachable!(),
     }
 }
 
 
 pub fn get_passkey() -> PasskeyCredential {
-    PasskeyCredential { 
-        id: "qA19jUJhKeCAUgQcucVp7RYRosqUv_dO4DTxxhobN0w".to_string(),
-        authenticator_data: Binary::from_base64("SZYN5YgOjGh0NBcPZHZgW4/krrmihjLHmVzzuoMdl2MFAAAAAA==").unwrap(), 
-        signature: Binary::from_base64("5uM7Ut/syEjDrTS9r1iSAvHUD/ib0y0ckDuTueqejZqIUlo3YI1mx69nNCI1k5Yy1k4G/5BYMfoidput7nhnhQ==").unwrap(), 
-        pubkey: Some(Binary::from_base64("BDh2fdKVC0AAoRvIT5c7Z0T2fOfal3B74EE4NHAH/eBawRMwRL9pt1OQllwKvhtaYFdp/gyVgUwqSRJ1wmIMvSY=").unwrap()), 
-        client_data: ClientData::new( base64_to_url(SIGN_MESSAGE_BASE64), "http://localhost:5173", false, None), 
-        user_handle: None
+    match signed_credential(&test_signers().passkey) {
+        Credential::Passkey(passkey) => passkey,
+        _ => unreachable!(),
     }
 }
 
@@ -69,42 +76,104 @@ pub fn base_credentials() -> Vec<Credential> {
 }
 
 
-pub fn with_key_msg(msg: ActionMsg, key: &String) -> ExecuteMsg {
-    ExecuteMsg::SessionActions(SessionAction {
-            msg: SessionActionMsg::WithSessionKey(WithSessionMsg {
-            session_key: key.to_string(),
-            msgs: msg,
-        }),
-        signed: None,
-    })
+pub fn get_mock_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier, Empty>  {
+    let mut deps = mock_dependencies();
+    deps.api = MockApi::default().with_prefix("stars");
+    deps
 }
 
 
-pub fn session_query(msg: SessionQueryMsg<QueryMsg>) -> QueryMsg {
-    QueryMsg::SessionQueries(msg)
+/// Mock dependencies of an account with Alice as its native credential
+pub fn get_account_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier, Empty>  {
+    let mut deps = get_mock_deps();
+    let (id, info) = native_grantee(ALICE_ADDR);
+    CREDENTIAL_INFOS.save(deps.as_mut().storage, id, &info).unwrap();
+    HAS_NATIVES.save(deps.as_mut().storage, &true).unwrap();
+    deps
 }
 
 
-pub fn default_cred_count() -> usize {
-    base_credentials().len()
-}
+/// Gas charged by the VM for a microsecond of the host crypto functions
+const GAS_PER_US: u64 = 1_000_000;
+
+/// Gas costs of the crypto functions from the `GasConfig` of `cosmwasm-vm` 2.x
+pub const SECP256K1_VERIFY_GAS: u64 = 154 * GAS_PER_US;
+pub const SECP256K1_RECOVER_GAS: u64 = 162 * GAS_PER_US;
+pub const SECP256R1_VERIFY_GAS: u64 = 279 * GAS_PER_US;
+pub const ED25519_VERIFY_GAS: u64 = 63 * GAS_PER_US;
 
 
-pub fn credential_data() -> CredentialData {
-    CredentialData::new(base_credentials(), Some(true))
+/// Mock API counting the signature checks and the gas that they cost in the VM
+pub struct CountingApi {
+    api             :   MockApi,
+    pub signatures  :   Cell<u32>,
+    pub gas         :   Cell<u64>,
 }
 
 
+impl CountingApi {
+    pub fn new() -> Self {
+        Self { api: MockApi::default().with_prefix("stars"), signatures: Cell::new(0), gas: Cell::new(0) }
+    }
+
+    /// Signature checks and their gas since the last call
+    pub fn take(&self) -> (u32, u64) {
+        (self.signatures.replace(0), self.gas.replace(0))
+    }
 
-pub fn cred_data_non_native() -> CredentialData {
-    CredentialData::new(base_credentials(), None)
+    fn count(&self, gas: u64) {
+        self.signatures.set(self.signatures.get() + 1);
+        self.gas.set(self.gas.get() + gas);
+    }
 }
 
 
-pub fn get_mock_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier, Empty>  {
-    let mut deps = mock_dependencies();
-    deps.api = MockApi::default().with_prefix("stars");
-    deps
+impl Api for CountingApi {
+    fn addr_validate(&self, human: &str) -> StdResult<Addr> {
+        self.api.addr_validate(human)
+    }
+
+    fn addr_canonicalize(&self, human: &str) -> StdResult<CanonicalAddr> {
+        self.api.addr_canonicalize(human)
+    }
+
+    fn addr_humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
+        self.api.addr_humanize(canonical)
+    }
+
+    fn secp256k1_verify(&self, hash: &[u8], signature: &[u8], pubkey: &[u8]) -> Result<bool, VerificationError> {
+        self.count(SECP256K1_VERIFY_GAS);
+        self.api.secp256k1_verify(hash, signature, pubkey)
+    }
+
+    fn secp256k1_recover_pubkey(&self, hash: &[u8], signature: &[u8], param: u8) -> Result<Vec<u8>, RecoverPubkeyError> {
+        self.count(SECP256K1_RECOVER_GAS);
+        self.api.secp256k1_recover_pubkey(hash, signature, param)
+    }
+
+    fn secp256r1_verify(&self, hash: &[u8], signature: &[u8], pubkey: &[u8]) -> Result<bool, VerificationError> {
+        self.count(SECP256R1_VERIFY_GAS);
+        self.api.secp256r1_verify(hash, signature, pubkey)
+    }
+
+    fn ed25519_verify(&self, message: &[u8], signature: &[u8], pubkey: &[u8]) -> Result<bool, VerificationError> {
+        self.count(ED25519_VERIFY_GAS);
+        self.api.ed25519_verify(message, signature, pubkey)
+    }
+
+    fn ed25519_batch_verify(
+        &self, 
+        messages: &[&[u8]], 
+        signatures: &[&[u8]], 
+        pubkeys: &[&[u8]]
+    ) -> Result<bool, VerificationError> {
+        self.count(ED25519_VERIFY_GAS * signatures.len() as u64);
+        self.api.ed25519_batch_verify(messages, signatures, pubkeys)
+    }
+
+    fn debug(&self, message: &str) {
+        self.api.debug(message)
+    }
 }
 
 
@@ -116,29 +185,24 @@ pub fn get_mock_env() -> Env {
 }
 
 
-pub fn get_messages() -> Option<Vec<String>> {
-    Some(vec![MESSAGE_TEXT.to_string()])
-}
-
-pub fn get_empty_messages() -> Option<Vec<String>> {
-    None
-}
-
-
 pub fn session_info() -> SessionInfo {
     SessionInfo {
         expiration: None,
         granter: None,
-        grantee: (BOB_ADDR.to_string(), CredentialInfo {
-            name: CredentialName::Native,
-            hrp: None,
-            extension: None,
-            address: Some(CredentialAddress::Bech32(Addr::unchecked(BOB_ADDR))),
-        }),
+        grantee: native_grantee(BOB_ADDR),
     }
 }
 
 
+/// Grantee with the same info as the one returned by the verification of the caller
+pub fn native_grantee(addr: &str) -> (String, CredentialInfo) {
+    let caller : Credential = Caller::from(addr).into();
+    let info = caller.verify(get_mock_deps().as_ref()).unwrap();
+    (caller.id(), info)
+}
+
+
 pub fn person_info(addr: &str) -> MessageInfo {
     message_info(&Addr::unchecked(addr), &[])
-}
\ No newline at end of file
+}
+
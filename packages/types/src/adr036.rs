@@ -0,0 +1,88 @@
+use saa_schema::saa_type;
+use smart_account_auth::cosmwasm_std::{from_json, to_json_binary, Binary, Coin, StdResult};
+
+
+/// Type of the message that Cosmos wallets put into the sign doc of `signArbitrary`
+pub const SIGN_DATA_TYPE : &str = "sign/MsgSignData";
+
+
+
+#[saa_type]
+pub struct StdFee {
+    pub amount          :   Vec<Coin>,
+    pub gas             :   String,
+}
+
+
+#[saa_type]
+pub struct SignDataValue {
+    pub data            :   Binary,
+    pub signer          :   String,
+}
+
+
+#[saa_type]
+pub struct SignDataMsg {
+    pub r#type          :   String,
+    pub value           :   SignDataValue,
+}
+
+
+/// Amino sign doc that Cosmos wallets sign in `signArbitrary` as defined in ADR-036
+#[saa_type]
+pub struct StdSignDoc {
+    pub account_number  :   String,
+    pub chain_id        :   String,
+    pub fee             :   StdFee,
+    pub memo            :   String,
+    pub msgs            :   Vec<SignDataMsg>,
+    pub sequence        :   String,
+}
+
+
+
+impl StdSignDoc {
+
+    pub fn new(data: &Binary, signer: &str) -> Self {
+        Self {
+            account_number: "0".to_string(),
+            chain_id: String::new(),
+            fee: StdFee { amount: vec![], gas: "0".to_string() },
+            memo: String::new(),
+            msgs: vec![SignDataMsg {
+                r#type: SIGN_DATA_TYPE.to_string(),
+                value: SignDataValue { data: data.clone(), signer: signer.to_string() },
+            }],
+            sequence: "0".to_string(),
+        }
+    }
+
+    /// The wrapped data and its signer if the doc follows ADR-036 i.e. has a single `MsgSignData` and no fees, chain or sequence
+    pub fn signed_data(&self) -> Option<(&Binary, &str)> {
+        match self.msgs.as_slice() {
+            [msg] if *self == StdSignDoc::new(&msg.value.data, &msg.value.signer) => {
+                Some((&msg.value.data, msg.value.signer.as_str()))
+            },
+            _ => None,
+        }
+    }
+}
+
+
+
+/// Wrap the data into an ADR-036 sign doc of the signer
+pub fn wrap_adr036(
+    data: &Binary, 
+    signer: &str
+) -> StdResult<Binary> {
+    to_json_binary(&StdSignDoc::new(data, signer))
+}
+
+
+/// Data wrapped into an ADR-036 sign doc together with its signer. `None` if the data isn't a valid sign doc
+pub fn unwrap_adr036(
+    data: &Binary
+) -> Option<(Binary, String)> {
+    let doc : StdSignDoc = from_json(data).ok()?;
+    doc.signed_data().map(|(data, signer)| (data.clone(), signer.to_string()))
+}
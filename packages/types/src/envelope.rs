@@ -0,0 +1,63 @@
+use saa_schema::saa_type;
+use smart_account_auth::cosmwasm_std::{BlockInfo, Timestamp};
+use crate::errors::ValidityError;
+
+
+/// Block height or time used as a bound of the validity of a signed envelope
+#[saa_type]
+pub enum BlockBound {
+    Height(u64),
+    Time(Timestamp),
+}
+
+
+
+/// Optional fields of the signed envelopes (next to `MsgDataToSign` ones) that limit 
+/// the blocks at which the payload can be used. Both bounds are inclusive
+#[saa_type]
+#[derive(Default)]
+pub struct ValidityBounds {
+    pub valid_after     :   Option<BlockBound>,
+    pub valid_until     :   Option<BlockBound>,
+}
+
+
+
+impl BlockBound {
+    fn reached(&self, block: &BlockInfo) -> bool {
+        match self {
+            BlockBound::Height(height) => block.height >= *height,
+            BlockBound::Time(time) => block.time >= *time,
+        }
+    }
+
+    fn passed(&self, block: &BlockInfo) -> bool {
+        match self {
+            BlockBound::Height(height) => block.height > *height,
+            BlockBound::Time(time) => block.time > *time,
+        }
+    }
+}
+
+
+
+impl ValidityBounds {
+
+    pub fn is_empty(&self) -> bool {
+        self.valid_after.is_none() && self.valid_until.is_none()
+    }
+
+    pub fn check(&self, block: &BlockInfo) -> Result<(), ValidityError> {
+        if let Some(after) = &self.valid_after {
+            if !after.reached(block) {
+                return Err(ValidityError::NotYetValid);
+            }
+        }
+        if let Some(until) = &self.valid_until {
+            if until.passed(block) {
+                return Err(ValidityError::Stale);
+            }
+        }
+        Ok(())
+    }
+}
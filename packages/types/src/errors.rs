@@ -0,0 +1,206 @@
+pub use smart_account_auth::errors::*;
+
+
+/// Errors of the extra rules that can be attached to session keys on top of their allowed actions
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum SessionPolicyError {
+    #[error("Spending limit exceeded for denom: {0}")]
+    SpendingLimit(String),
+
+    #[error("Session key has reached the rate limit of its window")]
+    RateLimit,
+
+    #[error("Message doesn't satisfy the constraint of the session for: {0}")]
+    Constraint(String),
+
+    #[error("Session key can't send messages of kind: {0}")]
+    ForbiddenMsg(String),
+
+    #[error("Session key with spending limits can't send the funds of messages of kind: {0}")]
+    UnmeteredMsg(String),
+
+    #[error("Session key can't send messages to contract: {0}")]
+    ContractNotAllowed(String),
+
+    #[error("Session key can't send execute message: {0}")]
+    ExecuteMsgNotAllowed(String),
+}
+
+
+
+/// Errors of the sessions created by a grantee of another session
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum SubSessionError {
+    #[error("Sub-session can't expire after its parent session")]
+    OutlivesParent,
+
+    #[error("Sub-session can only have actions allowed for its parent session")]
+    ExceedsParent,
+
+    #[error("Parent session is missing or expired")]
+    InvalidParent,
+
+    #[error("Sub-session of the same grantee already exists: {0}")]
+    AlreadyExists(String),
+}
+
+
+
+/// Errors of creating a session when there might be another one with the same key
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum SessionCreationError {
+    #[error("Session already exists: {0}")]
+    AlreadyExists(String),
+
+    #[error("Can't merge into a session of another grantee or granter")]
+    MergeMismatch,
+
+    #[error("Invalid session label: {0}")]
+    InvalidLabel(String),
+}
+
+
+
+/// Mismatches between the signed envelope and the one expected by the contract
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum EnvelopeError {
+    #[error("Signed data is not a valid envelope: {0}")]
+    Invalid(String),
+
+    #[error("Signed envelope has a mismatching chain id")]
+    ChainId,
+
+    #[error("Signed envelope has a mismatching contract address")]
+    Contract,
+
+    #[error("Signed envelope has a mismatching nonce. Expected: {0}")]
+    Nonce(u64),
+
+    #[error("Signed envelope has {0} messages instead of {1}")]
+    MessageCount(usize, usize),
+
+    #[error("Signed envelope has a mismatching message at index: {0}")]
+    Message(usize),
+
+    #[error("ADR-036 sign doc has been signed by another address: {0}")]
+    Adr036Signer(String),
+}
+
+
+
+/// Replay errors of the signed payloads used outside of the validity bounds of their envelopes.
+/// Kept next to the `ReplayError` of `smart-account-auth` that can't be extended from here
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ValidityError {
+    #[error("Signed payload is no longer valid")]
+    Stale,
+
+    #[error("Signed payload is not valid yet")]
+    NotYetValid,
+}
+
+
+
+/// Errors of restoring the exported auth state of another account
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum RestoreError {
+    #[error("Only the exporting account can restore its state")]
+    Unauthorized,
+
+    #[error("Can't restore the state into the account that has exported it")]
+    SameAccount,
+
+    #[error("Credentials have already been saved")]
+    AlreadyInitialized,
+
+    #[error("Hash of the exported state doesn't match")]
+    HashMismatch,
+
+    #[error("Invalid exported state: {0}")]
+    Invalid(String),
+}
+
+
+
+/// Errors of receiving packets with signed actions from other chains
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum IbcAuthError {
+    #[error("Packets from channel {0} are not allowed")]
+    ChannelNotAllowed(String),
+
+    #[error("Invalid packet with signed actions: {0}")]
+    InvalidPacket(String),
+}
+
+
+
+/// Errors of the auth library keeping the ones of the extensions typed so that the contracts can match on them
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum WasmAuthError {
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+
+    #[error(transparent)]
+    Policy(#[from] SessionPolicyError),
+
+    #[error(transparent)]
+    SubSession(#[from] SubSessionError),
+
+    #[error(transparent)]
+    Creation(#[from] SessionCreationError),
+
+    #[error(transparent)]
+    Envelope(#[from] EnvelopeError),
+
+    #[error(transparent)]
+    Validity(#[from] ValidityError),
+
+    #[error(transparent)]
+    Restore(#[from] RestoreError),
+
+    #[error(transparent)]
+    Ibc(#[from] IbcAuthError),
+}
+
+
+impl WasmAuthError {
+    pub fn generic<M: Into<String>>(msg: M) -> Self {
+        AuthError::generic(msg).into()
+    }
+}
+
+
+#[cfg(feature = "session")]
+impl From<SessionError> for WasmAuthError {
+    fn from(err: SessionError) -> Self {
+        AuthError::from(err).into()
+    }
+}
+
+
+impl From<StorageError> for WasmAuthError {
+    fn from(err: StorageError) -> Self {
+        AuthError::from(err).into()
+    }
+}
+
+
+impl From<CredentialError> for WasmAuthError {
+    fn from(err: CredentialError) -> Self {
+        AuthError::from(err).into()
+    }
+}
+
+
+impl From<ReplayError> for WasmAuthError {
+    fn from(err: ReplayError) -> Self {
+        AuthError::from(err).into()
+    }
+}
+
+
+impl From<crate::wasm::StdError> for WasmAuthError {
+    fn from(err: crate::wasm::StdError) -> Self {
+        AuthError::from(err).into()
+    }
+}
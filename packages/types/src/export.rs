@@ -0,0 +1,67 @@
+use saa_schema::saa_type;
+use sha2::{Digest, Sha256};
+use crate::{wasm::{to_json_vec, Binary, StdResult}, StoredCredentials};
+
+
+
+/// Complete auth state of an account that can be moved into a new contract
+#[saa_type]
+pub struct AuthState {
+    /// Address of the exporting account
+    pub account             :   String,
+
+    /// Credentials together with the sessions if enabled
+    pub credentials         :   StoredCredentials,
+
+    #[cfg(feature = "session")]
+    pub session_config      :   Option<crate::sessions::config::SessionConfig>,
+
+    /// Policies of the sessions by their keys
+    #[cfg(feature = "session")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub session_policies    :   Vec<(String, crate::sessions::policy::SessionPolicy)>,
+
+    /// Keys of the sub-sessions paired with the keys of their parents
+    #[cfg(feature = "session")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub session_parents     :   Vec<(String, String)>,
+
+    /// Labels of the sessions by their keys
+    #[cfg(feature = "session")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub session_labels      :   Vec<(String, String)>,
+}
+
+
+
+/// Exported auth state with its integrity hash. The hash only detects a corrupted state, 
+/// anyone can hash a state of their own so it doesn't prove where the export comes from
+#[saa_type]
+pub struct AuthStateExport {
+    pub state               :   AuthState,
+    /// SHA-256 of the JSON encoded state
+    pub hash                :   Binary,
+}
+
+
+
+impl AuthState {
+    /// Deterministic hash of the state. The records come from the storage in ascending order 
+    /// and the fields are always serialized in the same order
+    pub fn hash(&self) -> StdResult<Binary> {
+        let json = to_json_vec(self)?;
+        Ok(Binary::from(Sha256::digest(&json).to_vec()))
+    }
+}
+
+
+impl AuthStateExport {
+
+    pub fn new(state: AuthState) -> StdResult<Self> {
+        Ok(Self { hash: state.hash()?, state })
+    }
+
+    pub fn is_intact(&self) -> bool {
+        self.state.hash().is_ok_and(|hash| hash == self.hash)
+    }
+}
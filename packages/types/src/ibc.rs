@@ -0,0 +1,41 @@
+use saa_schema::saa_type;
+use smart_account_auth::msgs::SignedDataMsg;
+use crate::wasm::{to_json_binary, Binary};
+
+
+/// Packet relayed from another chain with actions signed by one of the stored credentials.
+/// The signed envelope must list the JSON encoded `msgs` and be bound to the chain of the account
+#[saa_type]
+pub struct SignedActionsPacket<M = String> {
+    pub msgs                :   Vec<M>,
+    pub signed              :   SignedDataMsg,
+}
+
+
+/// Acknowledgement written for the received packets in the format of ICS-20
+#[saa_type]
+pub enum SignedActionsAck {
+    Result(Binary),
+    Error(String),
+}
+
+
+
+impl SignedActionsAck {
+
+    pub fn success() -> Self {
+        Self::Result(Binary::from(vec![1u8]))
+    }
+
+    pub fn error(err: impl ToString) -> Self {
+        Self::Error(err.to_string())
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Result(_))
+    }
+
+    pub fn to_binary(&self) -> Binary {
+        to_json_binary(self).unwrap_or_default()
+    }
+}
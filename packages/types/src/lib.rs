@@ -1,8 +1,14 @@
 #[cfg(feature = "session")]
 pub mod sessions;
 pub mod stores;
+pub mod errors;
+pub mod adr036;
+pub mod envelope;
+pub mod export;
+pub mod merkle;
+#[cfg(feature = "ibc")]
+pub mod ibc;
 
-pub use smart_account_auth::errors;
 pub use smart_account_auth::cosmwasm_std as wasm;
 pub use saa_schema::{saa_type, serde, strum, strum_macros};
 
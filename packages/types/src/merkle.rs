@@ -0,0 +1,136 @@
+use saa_schema::{saa_type, QueryResponses};
+use sha2::{Digest, Sha256};
+use smart_account_auth::{CredentialId, CredentialInfo};
+use crate::wasm::{to_json_vec, Binary, StdResult};
+
+
+const LEAF_PREFIX : u8 = 0;
+const NODE_PREFIX : u8 = 1;
+
+
+
+/// Inclusion proof of a credential in the set stored by an account
+#[saa_type]
+pub struct CredentialProof {
+    pub id                  :   CredentialId,
+    pub info                :   CredentialInfo,
+    /// Sibling hashes from the leaf up to the root
+    pub proof               :   Vec<Binary>,
+    pub root                :   Binary,
+}
+
+
+/// Queries of the credential commitment that a contract can embed into its own query message
+#[saa_type]
+#[derive(QueryResponses)]
+pub enum CredentialsQueryMsg {
+    #[returns(Option<Binary>)]
+    CredentialsRoot {},
+
+    #[returns(CredentialProof)]
+    CredentialProof {
+        id: CredentialId,
+    },
+}
+
+
+
+impl CredentialProof {
+    pub fn verify(&self) -> bool {
+        verify_credential_proof(&self.root, &self.id, &self.info, &self.proof)
+    }
+}
+
+
+
+/// Leaf of a credential: `sha256(0x00 ‖ sha256(id) ‖ sha256(json(info)))`
+pub fn credential_leaf(
+    id: &str, 
+    info: &CredentialInfo
+) -> StdResult<[u8; 32]> {
+    let info = to_json_vec(info)?;
+    Ok(sha256(&[&[LEAF_PREFIX][..], &sha256(id.as_bytes())[..], &sha256(&info)[..]].concat()))
+}
+
+
+/// Root over the leaves ordered by the credential IDs. The pairs are sorted before hashing 
+/// so the proofs don't need positions and the last node of an odd level is moved up as it is
+pub fn merkle_root(
+    leaves: &[[u8; 32]]
+) -> Option<[u8; 32]> {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level.first().copied()
+}
+
+
+/// Sibling hashes of the leaf at the given index
+pub fn merkle_proof(
+    leaves: &[[u8; 32]], 
+    index: usize
+) -> Vec<Binary> {
+    let mut proof = vec![];
+    let mut level = leaves.to_vec();
+    let mut index = index;
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(Binary::from(sibling.to_vec()));
+        }
+        level = next_level(&level);
+        index /= 2;
+    }
+    proof
+}
+
+
+/// Whether the credential is a member of the set with the given root
+pub fn verify_credential_proof(
+    root: &Binary,
+    id: &str,
+    info: &CredentialInfo,
+    proof: &[Binary],
+) -> bool {
+    let leaf = match credential_leaf(id, info) {
+        Ok(leaf) => leaf,
+        Err(_) => return false,
+    };
+    proof
+        .iter()
+        .try_fold(leaf, |node, sibling| {
+            let sibling : [u8; 32] = sibling.as_slice().try_into().ok()?;
+            Some(hash_pair(&node, &sibling))
+        })
+        .is_some_and(|computed| computed.as_slice() == root.as_slice())
+}
+
+
+
+fn next_level(
+    level: &[[u8; 32]]
+) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_pair(left, right),
+            _ => pair[0],
+        })
+        .collect()
+}
+
+
+fn hash_pair(
+    a: &[u8; 32], 
+    b: &[u8; 32]
+) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    sha256(&[&[NODE_PREFIX][..], &left[..], &right[..]].concat())
+}
+
+
+fn sha256(
+    data: &[u8]
+) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
@@ -1,2 +1,5 @@
 pub mod queries;
-pub mod actions;
\ No newline at end of file
+pub mod actions;
+pub mod policy;
+pub mod config;
+pub mod events;
\ No newline at end of file
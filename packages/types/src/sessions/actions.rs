@@ -2,9 +2,10 @@
 use saa_schema::saa_type;
 use saa_schema::strum_macros::Display;
 use smart_account_auth::cosmwasm_std::Env;
-use smart_account_auth::{Session, SessionInfo, DerivableMsg};
+use smart_account_auth::{CredentialId, Session, SessionInfo, DerivableMsg, Expiration};
+use smart_account_auth::cosmwasm_std::ensure;
 use smart_account_auth::msgs::{SignedDataMsg, Action, AllowedActions, ActionDerivation};
-use crate::{errors::SessionError};
+use crate::{errors::{SessionCreationError, SessionError, SubSessionError, WasmAuthError}, sessions::{policy::SessionPolicy, queries::MsgToDerive}};
 
 #[cfg(not(feature = "multi"))]
 pub type MsgArg<D> = D;
@@ -13,24 +14,60 @@ pub type MsgArg<D> = Vec<D>;
 
 
 
+/// What to do when a session with the same key already exists
+#[saa_type]
+#[derive(Default, Display)]
+pub enum CreationMode {
+    /// Keep the existing session and return an error
+    Fail,
+    /// Overwrite the existing session (and its sub-sessions) and bump the nonce
+    #[default]
+    Replace,
+    /// Add the new actions to the existing session and take the new expiration
+    Merge,
+}
+
+
+
 #[saa_type]
 pub struct CreateSession {
     pub allowed_actions     :      AllowedActions,
     pub session_info        :      SessionInfo,
+    pub policy              :      Option<SessionPolicy>,
+    pub mode                :      Option<CreationMode>,
+    /// Appended to the key so that a grantee can have several distinct sessions
+    pub label               :      Option<String>,
 }
 
 
 
 #[saa_type]
 pub struct CreateSessionFrom<M : DerivableMsg> {
-    pub msgs                :      M,
+    /// Messages to execute right away and to derive the allowed actions from
+    pub msgs                :      Vec<MsgToDerive<M>>,
+    /// Derivation method for the messages that don't specify their own
     pub derivation          :      Option<ActionDerivation>,
     pub session_info        :      SessionInfo,
+    pub policy              :      Option<SessionPolicy>,
+    pub mode                :      Option<CreationMode>,
+    /// Appended to the key so that a grantee can have several distinct sessions
+    pub label               :      Option<String>,
 }
 
 
 
 
+/// Session created by the grantee of the parent session with a subset of its permissions
+#[saa_type]
+pub struct CreateSubSession {
+    pub parent_key          :      String,
+    pub allowed_actions     :      AllowedActions,
+    pub session_info        :      SessionInfo,
+    pub policy              :      Option<SessionPolicy>,
+}
+
+
+
 #[saa_type]
 pub struct WithSessionMsg<M> {
     pub msgs                :      MsgArg<M>,
@@ -38,6 +75,14 @@ pub struct WithSessionMsg<M> {
 }
 
 
+#[saa_type]
+pub struct UpdateSessionMsg {
+    pub session_key         :      String,
+    pub expiration          :      Option<Expiration>,
+    pub allowed_actions     :      Option<AllowedActions>,
+}
+
+
 #[saa_type]
 pub struct RevokeKeyMsg {
     pub session_key         :      String,
@@ -46,13 +91,29 @@ pub struct RevokeKeyMsg {
 
 
 
+/// Sessions to revoke at once. Only the expired ones can be removed 
+/// if they haven't been granted by the caller
+#[saa_type]
+pub enum RevokeSessionsMsg {
+    Keys(Vec<String>),
+    Grantee(CredentialId),
+    Granter(String),
+    All {},
+}
+
+
+
+
 #[saa_type]
 #[derive(Display)]
 pub enum SessionActionMsg<M : DerivableMsg> {
     CreateSession(CreateSession),
     CreateSessionFromMsg(CreateSessionFrom<M>),
     WithSessionKey(WithSessionMsg<M>),
+    CreateSubSession(CreateSubSession),
+    UpdateSession(UpdateSessionMsg),
     RevokeSession(RevokeKeyMsg),
+    RevokeSessions(RevokeSessionsMsg),
 }
 
 
@@ -103,12 +164,20 @@ impl<M: DerivableMsg> CreateSessionFrom<M> {
             _
         ) = self.session_info.checked_params(env, None)?;
         
-        let method = self.derivation.clone().unwrap_or_default();
-        let action = Action::new(&self.msgs, method)
-            .map_err(|_| SessionError::InvalidActions)?;
+        ensure!(!self.msgs.is_empty(), SessionError::InvalidActions);
+
+        let mut actions : Vec<Action> = Vec::with_capacity(self.msgs.len());
+        for msg in self.msgs.iter() {
+            let method = msg.method.clone().or(self.derivation.clone()).unwrap_or_default();
+            let action = Action::new(&msg.message, method)
+                .map_err(|_| SessionError::InvalidActions)?;
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
 
         Ok(Session {
-            actions: AllowedActions::Include(vec![action]),
+            actions: AllowedActions::Include(actions),
             expiration,
             grantee,
             granter,
@@ -117,3 +186,173 @@ impl<M: DerivableMsg> CreateSessionFrom<M> {
     }
 }
 
+
+
+impl CreateSubSession {
+    pub fn to_session(
+        &self, 
+        env: &Env,
+        parent: &Session
+    ) -> Result<Session, WasmAuthError> {
+        let session_info = SessionInfo {
+            granter: Some(parent.granter.clone()),
+            ..self.session_info.clone()
+        };
+        let (
+            granter,
+            grantee, 
+            expiration, 
+            actions
+        ) = session_info.checked_params(env, Some(&self.allowed_actions))?;
+
+        let session = Session {
+            actions,
+            expiration,
+            grantee,
+            granter,
+            nonce: 0,
+        };
+        check_parent_bounds(&session, parent)?;
+        Ok(session)
+    }
+}
+
+
+
+/// Check that the sub-session neither outlives its parent nor has any action the parent doesn't allow
+pub fn check_parent_bounds(
+    session: &Session,
+    parent: &Session
+) -> Result<(), SubSessionError> {
+    ensure!(expires_within(&session.expiration, &parent.expiration), SubSessionError::OutlivesParent);
+    
+    let subset = match (&parent.actions, &session.actions) {
+        (AllowedActions::Include(allowed), AllowedActions::Include(requested)) => {
+            requested.iter().all(|action| allowed.contains(action))
+        },
+        (AllowedActions::Include(_), _) => false,
+        _ => true,
+    };
+    ensure!(subset, SubSessionError::ExceedsParent);
+    Ok(())
+}
+
+
+
+/// Key of the session with an optional label. Labels can't contain slashes so that a labeled key never 
+/// collides with the key of a sub-session which ends with the base64 key of the child after a slash
+pub fn labeled_key(
+    session: &Session,
+    label: Option<&String>
+) -> Result<String, SessionCreationError> {
+    match label {
+        Some(label) => {
+            ensure!(
+                !label.is_empty() && label.len() <= 64 && !label.contains('/'),
+                SessionCreationError::InvalidLabel(label.clone())
+            );
+            Ok(format!("{}:{}", session.key(), label))
+        },
+        None => Ok(session.key()),
+    }
+}
+
+
+
+impl CreationMode {
+    /// Session to store under the key given the one that might already be there.
+    /// Expired sessions are always replaced
+    pub fn resolve(
+        &self,
+        env: &Env,
+        key: &str,
+        existing: Option<Session>,
+        session: Session,
+    ) -> Result<Session, SessionCreationError> {
+        let existing = match existing {
+            Some(existing) => existing,
+            None => return Ok(session),
+        };
+        let nonce = existing.nonce + 1;
+        if existing.expiration.is_expired(&env.block) {
+            return Ok(Session { nonce, ..session });
+        }
+        match self {
+            CreationMode::Fail => Err(SessionCreationError::AlreadyExists(key.to_string())),
+            CreationMode::Replace => Ok(Session { nonce, ..session }),
+            CreationMode::Merge => {
+                ensure!(
+                    existing.grantee == session.grantee && existing.granter == session.granter,
+                    SessionCreationError::MergeMismatch
+                );
+                Ok(Session {
+                    actions: merge_actions(existing.actions, session.actions),
+                    nonce,
+                    ..session
+                })
+            }
+        }
+    }
+}
+
+
+
+fn merge_actions(
+    existing: AllowedActions,
+    added: AllowedActions
+) -> AllowedActions {
+    match (existing, added) {
+        (AllowedActions::Include(mut actions), AllowedActions::Include(added)) => {
+            added.into_iter().for_each(|action| if !actions.contains(&action) {
+                actions.push(action);
+            });
+            AllowedActions::Include(actions)
+        },
+        (AllowedActions::Include(_), other) => other,
+        (other, _) => other,
+    }
+}
+
+
+
+fn expires_within(
+    expiration: &Expiration, 
+    parent: &Expiration
+) -> bool {
+    match (expiration, parent) {
+        (_, Expiration::Never {}) => true,
+        (Expiration::AtHeight(height), Expiration::AtHeight(parent)) => height <= parent,
+        (Expiration::AtTime(time), Expiration::AtTime(parent)) => time <= parent,
+        _ => false,
+    }
+}
+
+
+
+impl UpdateSessionMsg {
+    pub fn to_session(
+        &self, 
+        env: &Env,
+        session: &Session
+    ) -> Result<Session, SessionError> {
+        let session_info = SessionInfo {
+            grantee: session.grantee.clone(),
+            granter: Some(session.granter.clone()),
+            expiration: Some(self.expiration.clone().unwrap_or(session.expiration.clone())),
+        };
+        let (
+            granter,
+            grantee, 
+            expiration, 
+            actions
+        ) = session_info.checked_params(env, Some(self.allowed_actions.as_ref().unwrap_or(&session.actions)))?;
+
+        Ok(Session {
+            actions,
+            expiration,
+            grantee,
+            granter,
+            nonce: session.nonce,
+        })
+    }
+}
@@ -0,0 +1,46 @@
+use saa_schema::saa_type;
+use smart_account_auth::CredentialId;
+
+
+
+/// What happens when a session key is used after it has expired
+#[saa_type]
+#[derive(Default)]
+pub enum ExpiredKeyPolicy {
+    /// Remove the session and succeed without executing the messages
+    #[default]
+    Revoke,
+    /// Fail the transaction so that the caller sees the error. Since the failed transaction 
+    /// reverts its changes, the session is left to be pruned or revoked by anyone
+    Fail,
+}
+
+
+
+/// Principals that can create, update and revoke sessions on behalf of the account
+#[saa_type]
+#[derive(Default)]
+pub struct SessionAdmins {
+    /// Any stored native credential i.e. an address that can call the account directly
+    pub natives         :   bool,
+    /// IDs of the stored native credentials that can act as admins
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub credentials     :   Vec<CredentialId>,
+    /// Extra addresses that don't have to be stored as credentials
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses       :   Vec<String>,
+}
+
+
+
+/// Contract-wide settings of the session keys
+#[saa_type]
+#[derive(Default)]
+pub struct SessionConfig {
+    /// Prune up to this number of expired sessions every time a session action is handled
+    pub auto_prune      :   Option<u32>,
+    /// Behavior of `WithSessionKey` for the expired sessions. Revokes them by default
+    pub on_expired      :   Option<ExpiredKeyPolicy>,
+    /// Admins of the sessions. Without them the sessions are managed by the given admin or the account itself
+    pub admins          :   Option<SessionAdmins>,
+}
@@ -0,0 +1,175 @@
+use saa_schema::saa_type;
+use smart_account_auth::CredentialId;
+use smart_account_auth::cosmwasm_std::{Event, StdError, StdResult};
+
+
+/// Type of the event emitted when a session is created
+pub const SESSION_CREATED : &str = "session_created";
+/// Type of the event emitted when a session key is used to execute messages
+pub const SESSION_USED : &str = "session_used";
+/// Type of the event emitted when a session is revoked by its granter
+pub const SESSION_REVOKED : &str = "session_revoked";
+/// Type of the event emitted when an expired session is removed
+pub const SESSION_EXPIRED : &str = "session_expired";
+
+/// Prefix added by the chain to the types of the events emitted by contracts
+const WASM_PREFIX : &str = "wasm-";
+
+
+
+#[saa_type]
+pub struct SessionCreatedEvent {
+    pub session_key     :   String,
+    pub granter         :   String,
+    pub grantee         :   CredentialId,
+    pub nonce           :   u64,
+    /// Key of the parent session for sub-sessions
+    pub parent_key      :   Option<String>,
+}
+
+
+#[saa_type]
+pub struct SessionUsedEvent {
+    pub session_key     :   String,
+    pub nonce           :   u64,
+}
+
+
+#[saa_type]
+pub struct SessionRevokedEvent {
+    pub session_key     :   String,
+}
+
+
+#[saa_type]
+pub struct SessionExpiredEvent {
+    pub session_key     :   String,
+}
+
+
+
+/// Events emitted by the session actions that can be parsed back from the results of transactions
+#[saa_type]
+pub enum SessionEvent {
+    Created(SessionCreatedEvent),
+    Used(SessionUsedEvent),
+    Revoked(SessionRevokedEvent),
+    Expired(SessionExpiredEvent),
+}
+
+
+
+impl SessionEvent {
+
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            SessionEvent::Created(_) => SESSION_CREATED,
+            SessionEvent::Used(_) => SESSION_USED,
+            SessionEvent::Revoked(_) => SESSION_REVOKED,
+            SessionEvent::Expired(_) => SESSION_EXPIRED,
+        }
+    }
+
+
+    pub fn session_key(&self) -> &str {
+        match self {
+            SessionEvent::Created(e) => &e.session_key,
+            SessionEvent::Used(e) => &e.session_key,
+            SessionEvent::Revoked(e) => &e.session_key,
+            SessionEvent::Expired(e) => &e.session_key,
+        }
+    }
+
+
+    /// Parse an event emitted by the contract or the one returned by the chain with the `wasm-` prefix.
+    /// Returns `None` for the events of other types
+    pub fn from_event(event: &Event) -> StdResult<Option<Self>> {
+        let ty = event.ty.strip_prefix(WASM_PREFIX).unwrap_or(&event.ty);
+        let key = || attribute(event, "session_key");
+
+        let parsed = match ty {
+            SESSION_CREATED => SessionEvent::Created(SessionCreatedEvent {
+                session_key: key()?,
+                granter: attribute(event, "granter")?,
+                grantee: attribute(event, "grantee")?,
+                nonce: number(event, "nonce")?,
+                parent_key: attribute(event, "parent_key").ok(),
+            }),
+            SESSION_USED => SessionEvent::Used(SessionUsedEvent {
+                session_key: key()?,
+                nonce: number(event, "nonce")?,
+            }),
+            SESSION_REVOKED => SessionEvent::Revoked(SessionRevokedEvent { session_key: key()? }),
+            SESSION_EXPIRED => SessionEvent::Expired(SessionExpiredEvent { session_key: key()? }),
+            _ => return Ok(None),
+        };
+        Ok(Some(parsed))
+    }
+}
+
+
+
+impl From<SessionEvent> for Event {
+    fn from(event: SessionEvent) -> Self {
+        let ty = event.event_type();
+        match event {
+            SessionEvent::Created(e) => {
+                let created = Event::new(ty)
+                    .add_attribute("session_key", e.session_key)
+                    .add_attribute("granter", e.granter)
+                    .add_attribute("grantee", e.grantee)
+                    .add_attribute("nonce", e.nonce.to_string());
+                match e.parent_key {
+                    Some(parent_key) => created.add_attribute("parent_key", parent_key),
+                    None => created,
+                }
+            },
+            SessionEvent::Used(e) => Event::new(ty)
+                .add_attribute("session_key", e.session_key)
+                .add_attribute("nonce", e.nonce.to_string()),
+            SessionEvent::Revoked(e) => Event::new(ty)
+                .add_attribute("session_key", e.session_key),
+            SessionEvent::Expired(e) => Event::new(ty)
+                .add_attribute("session_key", e.session_key),
+        }
+    }
+}
+
+
+
+/// Session events in the order they appear in the list. Events of other types are skipped
+pub fn parse_session_events(
+    events: &[Event]
+) -> StdResult<Vec<SessionEvent>> {
+    events
+        .iter()
+        .filter_map(|e| SessionEvent::from_event(e).transpose())
+        .collect()
+}
+
+
+
+fn attribute(
+    event: &Event, 
+    key: &str
+) -> StdResult<String> {
+    event.attributes
+        .iter()
+        .find(|a| a.key == key)
+        .map(|a| a.value.clone())
+        .ok_or_else(|| StdError::generic_err(
+            format!("Missing attribute '{}' in the event '{}'", key, event.ty)
+        ))
+}
+
+
+fn number(
+    event: &Event, 
+    key: &str
+) -> StdResult<u64> {
+    attribute(event, key)?
+        .parse::<u64>()
+        .map_err(|e| StdError::generic_err(
+            format!("Invalid attribute '{}' in the event '{}': {}", key, event.ty, e)
+        ))
+}
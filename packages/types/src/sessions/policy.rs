@@ -0,0 +1,184 @@
+use saa_schema::saa_type;
+use smart_account_auth::cosmwasm_std::{BlockInfo, Uint128};
+
+
+
+/// Length of a window used for the limits of a session
+#[saa_type]
+pub enum Period {
+    /// Number of blocks since the start of the window
+    Blocks(u64),
+    /// Number of seconds since the start of the window
+    Seconds(u64),
+}
+
+
+
+/// Height and time (in seconds) of the block that marks a start of a window
+#[saa_type]
+pub struct BlockMark {
+    pub height  :   u64,
+    pub time    :   u64,
+}
+
+
+
+#[saa_type]
+pub struct PeriodLimit {
+    pub amount  :   Uint128,
+    pub period  :   Period,
+}
+
+
+
+/// Maximum amount of a denom that a session key can spend
+#[saa_type]
+pub struct SpendingLimit {
+    pub denom       :   String,
+    /// Cap over the whole lifetime of the session
+    pub total       :   Option<Uint128>,
+    /// Cap that resets every time the period has passed
+    pub per_period  :   Option<PeriodLimit>,
+}
+
+
+
+/// Maximum number of actions a session key can perform within a rolling window
+#[saa_type]
+pub struct RateLimit {
+    pub max_actions :   u32,
+    pub window      :   Period,
+}
+
+
+
+/// Condition that a value of a message field must satisfy
+#[saa_type]
+pub enum ConstraintRule {
+    Equals(String),
+    OneOf(Vec<String>),
+    MaxAmount(Uint128),
+    Prefix(String),
+}
+
+
+
+/// Rule for a field of a message located with a dot separated path starting with 
+/// the name of the variant e.g. `transfer_token.to`. Messages of other variants are not affected.
+/// A `*` segment matches every element of a list e.g. `send.amount.*.amount` and all of them
+/// must satisfy the rule. `MaxAmount` limits the sum of the matched values
+#[saa_type]
+pub struct ArgConstraint {
+    pub path    :   String,
+    pub rule    :   ConstraintRule,
+}
+
+
+
+/// Rules for the `CosmosMsg`s found at any depth of the executed messages including
+/// the ones inside of the payloads of `WasmMsg::Execute`
+#[saa_type]
+#[derive(Default)]
+pub struct CosmosMsgPolicy {
+    /// Contracts that can be executed, migrated or have their admin changed
+    pub allowed_contracts       :   Option<Vec<String>>,
+    /// Names of the execute messages (their top level key) that can be sent to contracts
+    pub allowed_execute_msgs    :   Option<Vec<String>>,
+    /// Kinds of messages that can't be used e.g. `wasm.migrate`, `wasm.update_admin` or `bank`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forbidden               :   Vec<String>,
+}
+
+
+
+/// Extra rules checked every time a session key is used
+#[saa_type]
+#[derive(Default)]
+pub struct SessionPolicy {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spending_limits     :   Vec<SpendingLimit>,
+    pub rate_limit          :   Option<RateLimit>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraints         :   Vec<ArgConstraint>,
+    pub cosmos_msgs         :   Option<CosmosMsgPolicy>,
+}
+
+
+
+/// Amounts of a denom already spent by a session key
+#[saa_type]
+pub struct DenomSpending {
+    pub denom           :   String,
+    pub total           :   Uint128,
+    pub period          :   Uint128,
+    pub period_start    :   BlockMark,
+}
+
+
+
+
+impl From<&BlockInfo> for BlockMark {
+    fn from(block: &BlockInfo) -> Self {
+        Self {
+            height: block.height,
+            time: block.time.seconds(),
+        }
+    }
+}
+
+
+impl Period {
+    pub fn elapsed(&self, since: &BlockMark, block: &BlockInfo) -> bool {
+        match self {
+            Period::Blocks(blocks) => block.height >= since.height.saturating_add(*blocks),
+            Period::Seconds(secs) => block.time.seconds() >= since.time.saturating_add(*secs),
+        }
+    }
+}
+
+
+impl DenomSpending {
+    pub fn new(denom: &str, block: &BlockInfo) -> Self {
+        Self {
+            denom: denom.to_string(),
+            total: Uint128::zero(),
+            period: Uint128::zero(),
+            period_start: block.into(),
+        }
+    }
+}
+
+
+impl SessionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.spending_limits.is_empty() && self.rate_limit.is_none() && self.constraints.is_empty() && self.cosmos_msgs.is_none()
+    }
+}
+
+
+
+impl ConstraintRule {
+    /// Whether the value of a field (converted to a string) satisfies the rule
+    pub fn allows(&self, value: &str) -> bool {
+        match self {
+            ConstraintRule::Equals(expected) => value == expected,
+            ConstraintRule::OneOf(options) => options.iter().any(|o| o == value),
+            ConstraintRule::Prefix(prefix) => value.starts_with(prefix.as_str()),
+            ConstraintRule::MaxAmount(max) => value
+                .parse::<u128>()
+                .map(|amount| Uint128::new(amount) <= *max)
+                .unwrap_or(false),
+        }
+    }
+}
+
+
+
+impl CosmosMsgPolicy {
+    /// Whether a kind of a message like `wasm.migrate` is forbidden directly or through its category
+    pub fn forbids(&self, kind: &str) -> bool {
+        self.forbidden.iter().any(|f| 
+            kind == f || kind.strip_prefix(f.as_str()).is_some_and(|rest| rest.starts_with('.'))
+        )
+    }
+}
@@ -4,7 +4,8 @@ use saa_schema::strum_macros::{Display, EnumString, EnumDiscriminants};
 use saa_schema::QueryResponses;
 use smart_account_auth::cosmwasm_std::Binary;
 use smart_account_auth::msgs::{Action, ActionDerivation, AllQueryDerivation};
-use smart_account_auth::{SessionError, DerivableMsg};
+use smart_account_auth::{SessionError, DerivableMsg, Session};
+use crate::sessions::policy::SessionPolicy;
 
 
 #[saa_type]
@@ -36,6 +37,11 @@ where
         method: Option<ActionDerivation>,
     },
 
+    #[returns(SessionResponse)]
+    Session {
+        session_key: String,
+    },
+
 }
 
 
@@ -59,6 +65,16 @@ where
 }
 
 
+#[saa_type]
+pub struct SessionResponse {
+    pub session             :   Session,
+    pub policy              :   Option<SessionPolicy>,
+    /// Number of actions left in the current window of a rate limited session
+    pub remaining_actions   :   Option<u32>,
+}
+
+
+
 #[saa_type]
 pub struct QueryResTemplate {
     pub data: Option<Binary>,
@@ -4,6 +4,8 @@ pub use cw_storage_plus_one as cw_storage_plus;
 use cw_storage_plus::{Item, Map};
 use smart_account_auth::{CredentialInfo, CredentialId};
 use crate::wasm::{StdError, Storage, Order};
+#[cfg(feature = "session")]
+use crate::wasm::{BlockInfo, Empty};
 use crate::serde::{de::DeserializeOwned, Serialize};
 use crate::errors::StorageError;
 
@@ -20,6 +22,10 @@ pub const HAS_NATIVES : Item<bool> = Item::new("cw_auth_hn");
 pub const CREDENTIAL_INFOS: Map<CredentialId, CredentialInfo> = Map::new("cw_auth_creds");
 
 
+/// Merkle root over the stored credentials updated on every change
+pub const CREDENTIALS_ROOT : Item<crate::wasm::Binary> = Item::new("cw_auth_creds_root");
+
+
 /// Current account number or nonce that must be used for replay attack protection
 pub const ACCOUNT_NUMBER : Item<u64> = Item::new("cw_auth_an");
 
@@ -29,6 +35,62 @@ pub const ACCOUNT_NUMBER : Item<u64> = Item::new("cw_auth_an");
 pub const SESSIONS: Map<String, smart_account_auth::Session> = Map::new("cw_auth_ses");
 
 
+/// Extra rules attached to session keys
+#[cfg(feature = "session")]
+pub const SESSION_POLICIES: Map<String, crate::sessions::policy::SessionPolicy> = Map::new("cw_auth_ses_pol");
+
+
+/// Amounts already spent by session keys that have spending limits
+#[cfg(feature = "session")]
+pub const SESSION_SPENDING: Map<String, Vec<crate::sessions::policy::DenomSpending>> = Map::new("cw_auth_ses_spent");
+
+
+/// Blocks at which rate limited session keys have been used within their current window
+#[cfg(feature = "session")]
+pub const SESSION_USAGE: Map<String, Vec<crate::sessions::policy::BlockMark>> = Map::new("cw_auth_ses_use");
+
+
+/// Index of session keys expiring at a block height
+#[cfg(feature = "session")]
+pub const SESSION_EXPIRY_HEIGHTS: Map<(u64, String), Empty> = Map::new("cw_auth_ses_exh");
+
+
+/// Index of session keys expiring at a time (in nanoseconds)
+#[cfg(feature = "session")]
+pub const SESSION_EXPIRY_TIMES: Map<(u64, String), Empty> = Map::new("cw_auth_ses_ext");
+
+
+/// Parent keys of the sessions created by grantees of other sessions
+#[cfg(feature = "session")]
+pub const SESSION_PARENTS: Map<String, String> = Map::new("cw_auth_ses_par");
+
+
+/// Keys of sub-sessions grouped by the key of their parent
+#[cfg(feature = "session")]
+pub const SESSION_CHILDREN: Map<(String, String), Empty> = Map::new("cw_auth_ses_chl");
+
+
+/// Labels of the sessions created with one
+#[cfg(feature = "session")]
+pub const SESSION_LABELS: Map<String, String> = Map::new("cw_auth_ses_lbl");
+
+
+/// Keys of the labeled sessions by their granter, grantee and label
+#[cfg(feature = "session")]
+pub const LABELED_SESSIONS: Map<(String, String, String), String> = Map::new("cw_auth_ses_lbk");
+
+
+/// Contract-wide settings of the session keys
+#[cfg(feature = "session")]
+pub const SESSION_CONFIG: Item<crate::sessions::config::SessionConfig> = Item::new("cw_auth_ses_cfg");
+
+
+
+/// Channels from which the packets with signed actions are accepted
+#[cfg(feature = "ibc")]
+pub const IBC_CHANNELS: Map<String, crate::wasm::Empty> = Map::new("cw_auth_ibc_ch");
+
+
 
 // Feauture only because not used elsewhere
 pub fn item_exist<T>(
@@ -70,13 +132,13 @@ pub fn map_get<T>(
 pub fn map_save<T>(
     storage: &mut dyn Storage,
     map: &Map<String, T>,
-    key: &String,
+    key: &str,
     value: &T,
     name: &str
 ) -> Result<(), StorageError> 
     where T: Serialize + DeserializeOwned
 {
-    map.save(storage, key.clone(), value)
+    map.save(storage, key.to_string(), value)
     .map_err(|e| StorageError::Write(name.to_string(), e.to_string()))
 }
 
@@ -118,4 +180,33 @@ pub fn delete_map_records<V>(
 {
     map.clear(storage);
     Ok(())
-}
\ No newline at end of file
+}
+
+
+
+/// Keys of the sessions that have expired at the given block using the expiration indexes
+#[cfg(feature = "session")]
+pub fn expired_session_keys(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    limit: usize,
+) -> Result<Vec<String>, StorageError> {
+    let by_height = SESSION_EXPIRY_HEIGHTS.keys(
+        storage, 
+        None, 
+        Some(cw_storage_plus::Bound::exclusive((block.height.saturating_add(1), String::new()))), 
+        Order::Ascending
+    );
+    let by_time = SESSION_EXPIRY_TIMES.keys(
+        storage, 
+        None, 
+        Some(cw_storage_plus::Bound::exclusive((block.time.nanos().saturating_add(1), String::new()))), 
+        Order::Ascending
+    );
+    by_height
+        .chain(by_time)
+        .take(limit)
+        .map(|k| k.map(|(_, key)| key))
+        .collect::<Result<Vec<String>, StdError>>()
+        .map_err(|e| StorageError::Read("session expirations".to_string(), e.to_string()))
+}